@@ -0,0 +1,28 @@
+stl v0 i0
+set v1 0
+non
+cmp v1 v0
+jon l
+rej
+guess b0
+cmp b0 1
+jon ne
+jmp 12
+iadd v1 1
+jmp 2
+non
+set v2 v1
+iadd v2 1
+cmp v2 v0
+jon l
+rej
+stnb b1 i0 v1
+stnb b2 i0 v2
+cmp b1 1
+jon e
+rej
+cmp b2 1
+jon e
+rej
+acc
+end
@@ -0,0 +1,26 @@
+stl v0 i0
+set v1 0
+set v2 3
+non
+cmp v1 i1
+jon l
+jmp 11
+set v[v2] 0
+iadd v1 1
+iadd v2 1
+jmp 3
+non
+set v1 0
+non
+cmp v1 v0
+jon l
+jmp 24
+set v2 v1
+imod v2 i1
+iadd v2 3
+stnb b0 i0 v1
+iadd v[v2] b0
+iadd v1 1
+jmp 13
+non
+end
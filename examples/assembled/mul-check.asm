@@ -0,0 +1,13 @@
+set b0 0
+set b1 0
+set v0 i0
+imul v0 i1
+cmp v0 i2
+jon ne
+set b0 1
+set v1 i2
+imod v1 i1
+cmp v1 0
+jon ne
+set b1 1
+end
@@ -0,0 +1,25 @@
+set v0 0
+set v1 0
+set v2 0
+set v3 0
+set v1 0
+clf
+non
+stnb v0 i0 v1
+stnb v2 i1 v1
+badc v0 v2
+iadd v1 1
+cmp v1 i2
+jon g
+jmp 6
+set v1 0
+clf
+non
+stnb v3 i0 v1
+stnb v2 i0 v1
+badc v3 v2
+iadd v1 1
+cmp v1 i2
+jon g
+jmp 16
+end
@@ -0,0 +1,65 @@
+set b0 0
+set b1 0
+set v0 0
+non
+read b0 i0
+jon ne
+jmp 10
+right i0
+iadd v0 1
+jmp 3
+non
+set v1 v0
+set v2 0
+set v3 0
+set v4 0
+non
+set v4 v2
+iadd v4 v2
+iadd v4 1
+cmp v4 v0
+jon l
+jmp 60
+non
+cmp v1 v2
+jon ne
+jmp 35
+jon g
+jmp 31
+left i0
+isub v1 1
+jmp 22
+non
+right i0
+iadd v1 1
+jmp 22
+non
+read b0 i0
+set v3 v0
+isub v3 v2
+isub v3 1
+non
+cmp v1 v3
+jon ne
+jmp 53
+jon g
+jmp 49
+left i0
+isub v1 1
+jmp 40
+non
+right i0
+iadd v1 1
+jmp 40
+non
+read b1 i0
+cmp b0 b1
+jon e
+jmp 62
+iadd v2 1
+jmp 15
+non
+acc
+non
+rej
+end
@@ -0,0 +1,21 @@
+stl v0 i0
+set v1 0
+non
+cmp v1 v0
+jon l
+jmp 16
+set v2 v0
+isub v2 v1
+isub v2 1
+stnb v3 i0 v1
+stnb v4 i0 v2
+cmp v3 v4
+jon e
+jmp 18
+iadd v1 1
+jmp 2
+non
+acc
+non
+rej
+end
@@ -0,0 +1,11 @@
+set b0 0
+set b1 0
+non
+read b1 i0
+jon ne
+jmp 9
+xor b0 b1
+right i0
+jmp 2
+non
+end
@@ -0,0 +1,32 @@
+; expression temporaries: v3
+set v0 0
+set v1 0
+set v2 0
+set v3 0
+stl v0 i0
+non
+set v1 0
+set v2 1
+non
+cmp v2 v0
+jon l
+jmp 21
+rand b0
+set v3 v1
+iadd v3 v1
+iadd v3 b0
+set v1 v3
+set v3 v2
+iadd v3 v2
+set v2 v3
+jmp 8
+non
+cmp v1 v0
+jon l
+jmp 5
+stnb b0 i0 v1
+cmp b0 1
+jon e
+rej
+acc
+end
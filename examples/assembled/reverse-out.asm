@@ -0,0 +1,11 @@
+stl v0 i0
+non
+cmp v0 0
+jon g
+jmp 9
+isub v0 1
+stnb b0 i0 v0
+out b0
+jmp 1
+non
+end
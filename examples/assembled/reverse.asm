@@ -0,0 +1,22 @@
+set b0 0
+set v0 0
+stl v1 i0
+set v2 0
+set v3 0
+set b1 0
+non
+cmp v2 v1
+jon l
+jmp 18
+set v3 v1
+isub v3 v2
+isub v3 1
+stnb b2 i0 v2
+snb v0 b2 v3
+xor b1 b2
+iadd v2 1
+jmp 6
+non
+cmp v0 i0
+stf b0 e
+end
@@ -0,0 +1,21 @@
+; two-tape palindrome check: copy the input to the work tape, rewind the
+; input head, then compare the input forwards against the copy backwards
+tapes 2
+alphabet 0 1
+start copy
+accept accept
+reject reject
+
+copy 0 _ -> copy 0 R R
+copy 1 _ -> copy 1 R R
+copy _ _ -> rewind _ L S
+
+rewind 0 _ -> rewind _ L S
+rewind 1 _ -> rewind _ L S
+rewind _ _ -> check _ R L
+
+check 0 0 -> check 0 R L
+check 1 1 -> check 1 R L
+check 0 1 -> reject 1 S S
+check 1 0 -> reject 0 S S
+check _ _ -> accept _ S S
@@ -0,0 +1,275 @@
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+pub struct Disassembler
+{
+    pub program_lines:  Vec<String>,
+}
+
+impl Default for Disassembler
+{
+    fn default() -> Self
+    {
+        return Self::new();
+    }
+}
+
+impl Disassembler
+{
+    pub fn new() -> Self
+    {
+        Self
+        {
+            program_lines:  Vec::new(),
+        }
+    }
+
+    pub fn load_file(&mut self, file_path: impl AsRef<Path>)
+    {
+        let file: File = File::open(file_path).expect("Could not open file");
+        let io_file_lines:io::Result<Vec<String>> = BufReader::new(file).lines().collect();
+        self.program_lines = io_file_lines.expect("Could not read program lines");
+    }
+
+    pub fn decompile_program(&mut self, path: &str)
+    {
+        let sbasic_lines: Vec<String> = self.disassemble();
+
+        let f = File::create(path).expect("unable to create file");
+        let mut f = BufWriter::new(f);
+
+        for sbasic_line in sbasic_lines
+        {
+            write!(f, "{}\n", sbasic_line).expect("unable to write");
+        }
+    }
+
+    fn label_name(line_number: usize) -> String
+    {
+        return format!("line-{line_number}");
+    }
+
+    fn jump_target(operand: &str) -> usize
+    {
+        let target: Option<usize> = match operand.strip_prefix("0x")
+        {
+            Some(hex) => usize::from_str_radix(hex, 16).ok(),
+            None => operand.parse::<usize>().ok(),
+        };
+
+        return target.unwrap_or_else(|| panic!("Unsupported jump target: {}", operand));
+    }
+
+    fn flag_name(condition: &str) -> &'static str
+    {
+        match condition
+        {
+            "e" => "equal",
+            "ne" => "not-equal",
+            "g" => "greater",
+            "ge" => "greater-or-equal",
+            "l" => "less",
+            "le" => "less-or-equal",
+            "c" => "carry",
+            "nc" => "no-carry",
+            "b" => "borrow",
+            "nb" => "no-borrow",
+            "" => "",
+            _other => panic!("Invalid condition: {}", condition),
+        }
+    }
+
+    fn translate_instruction(tokens: &[&str], instruction: &str) -> String
+    {
+        let operand1: &str = tokens[1];
+        let operand2: &str = tokens[2];
+        let operand3: &str = tokens[3];
+
+        match tokens[0]
+        {
+            "set" => format!("set {operand1} to {operand2}"),
+            "stl" => format!("set {operand1} to length-of {operand2}"),
+            "stnb" => format!("set {operand1} to bit {operand3} of {operand2}"),
+            "iadd" => format!("int-add {operand2} to {operand1}"),
+            "isub" => format!("int-subtract {operand2} from {operand1}"),
+            "imul" => format!("int-multiply {operand2} into {operand1}"),
+            "idiv" => format!("int-divide {operand1} by {operand2}"),
+            "imod" => format!("int-modulo {operand1} by {operand2}"),
+            "and" => format!("bitwise-and {operand2} into {operand1}"),
+            "or" => format!("bitwise-or {operand2} into {operand1}"),
+            "xor" => format!("bitwise-xor {operand2} into {operand1}"),
+            "not" => format!("bitwise-not {operand1}"),
+            "snb" => format!("set bit {operand3} of {operand1} to {operand2}"),
+            "stf" => format!("set {operand1} to flag {}", Self::flag_name(operand2)),
+            "left" => format!("move-head-left {operand1}"),
+            "right" => format!("move-head-right {operand1}"),
+            "rew" => format!("rewind-head {operand1}"),
+            "read" => format!("read-head {operand2} into {operand1}"),
+            "badd" => format!("bit-add {operand2} to {operand1}"),
+            "badc" => format!("bit-add {operand2} to {operand1} with-carry"),
+            "bsub" => format!("bit-subtract {operand2} from {operand1}"),
+            "bsbu" => format!("bit-subtract {operand2} from {operand1} with-borrow"),
+            "bsr" => format!("shift {operand1} right by {operand2}"),
+            "bsl" => format!("shift {operand1} left by {operand2}"),
+            "cmp" => format!("compare {operand1} to {operand2}"),
+            "clf" => "clear-flags".to_string(),
+            "jmp" => format!("go-to {}", Self::label_name(Self::jump_target(operand1))),
+            "jon" => format!("skip-next-if {}", Self::flag_name(operand1)).trim_end().to_string(),
+            "end" => "end".to_string(),
+            "acc" => "accept".to_string(),
+            "rej" => "reject".to_string(),
+            "out" => format!("output {operand1}"),
+            "guess" => format!("guess {operand1}"),
+            "rand" => format!("random {operand1}"),
+            _other => panic!("Unknown operator: {}", instruction),
+        }
+    }
+
+    pub fn disassemble(&mut self) -> Vec<String>
+    {
+        // comments are kept but, as on the device, do not count as lines
+        let mut instructions: Vec<(Vec<&str>, &String)> = Vec::new();
+        let mut comments: Vec<(usize, &String)> = Vec::new();
+
+        for line in self.program_lines.iter()
+        {
+            if line.starts_with(";")
+            {
+                comments.push((instructions.len(), line));
+                continue;
+            }
+
+            let mut tokens: Vec<&str> = line.split_whitespace().collect::<Vec<&str>>();
+
+            // push empty values to ensure have 1 operators + 3 operands
+            tokens.extend(std::iter::repeat_n("", 4));
+
+            instructions.push((tokens, line));
+        }
+
+        let jump_targets: BTreeSet<usize> = instructions.iter()
+            .filter(|(tokens, _)| tokens[0] == "jmp")
+            .map(|(tokens, _)| Self::jump_target(tokens[1]))
+            .collect::<BTreeSet<usize>>();
+
+        // the assembler appends its own end
+        let mut program_length: usize = instructions.len();
+        if program_length > 0 && instructions[program_length - 1].0[0] == "end" && !jump_targets.contains(&(program_length - 1))
+        {
+            program_length -= 1;
+        }
+
+        let mut sbasic_lines: Vec<String> = Vec::new();
+        let mut comments = comments.into_iter().peekable();
+
+        for line_number in 0..program_length
+        {
+            while let Some((_, comment)) = comments.next_if(|(position, _)| *position <= line_number)
+            {
+                sbasic_lines.push(comment.to_string());
+            }
+
+            let (tokens, instruction) = &instructions[line_number];
+            let after_skip: bool = line_number > 0 && instructions[line_number - 1].0[0] == "jon";
+
+            if jump_targets.contains(&line_number)
+            {
+                if after_skip
+                {
+                    // a label compiles to a line of its own, which the skip
+                    // would land on; route both outcomes through jumps instead
+                    sbasic_lines.push(format!("go-to {}", Self::label_name(line_number)));
+                    sbasic_lines.push(format!("go-to {}", Self::label_name(line_number + 1)));
+                }
+                sbasic_lines.push(format!("label {}", Self::label_name(line_number)));
+            }
+
+            match tokens[0]
+            {
+                // padding is dropped unless a skip depends on it
+                "non" if after_skip && !jump_targets.contains(&line_number) =>
+                {
+                    sbasic_lines.push(String::new());
+                }
+                "non" => {}
+                _other =>
+                {
+                    sbasic_lines.push(Self::translate_instruction(tokens, instruction));
+                }
+            }
+
+            if jump_targets.contains(&line_number) && after_skip && !jump_targets.contains(&(line_number + 1))
+            {
+                sbasic_lines.push(format!("label {}", Self::label_name(line_number + 1)));
+            }
+        }
+
+        for (_, comment) in comments
+        {
+            sbasic_lines.push(comment.to_string());
+        }
+
+        // jumps past the last instruction land on the appended end
+        for jump_target in jump_targets.range(program_length..)
+        {
+            sbasic_lines.push(format!("label {}", Self::label_name(*jump_target)));
+        }
+
+        return sbasic_lines;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::{
+        fs,
+        path::PathBuf,
+    };
+
+    use super::Disassembler;
+    use crate::{
+        optimize_program,
+        Assembler,
+    };
+
+    // SBASIC adds a line for every label, so the programs are compared once
+    // the optimizer has removed the padding and renumbered the jumps
+    fn assert_round_trip(example: &str)
+    {
+        let example_path: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../examples").join(example);
+        let sbasic_path: PathBuf = std::env::temp_dir().join(format!("munin-round-trip-{}-{}.mns1", std::process::id(), example));
+
+        let mut disassembler: Disassembler = Disassembler::new();
+        disassembler.load_file(&example_path);
+        fs::write(&sbasic_path, disassembler.disassemble().join("\n") + "\n").unwrap();
+
+        let mut assembler: Assembler = Assembler::new();
+        assembler.load_file(&sbasic_path);
+        let reassembled: Vec<String> = assembler.assemble();
+        fs::remove_file(&sbasic_path).unwrap();
+
+        let original: Vec<String> = fs::read_to_string(&example_path).unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect::<Vec<String>>();
+
+        assert_eq!(optimize_program(&original), optimize_program(&reassembled));
+    }
+
+    #[test]
+    fn add_round_trips()
+    {
+        assert_round_trip("add.asm");
+    }
+
+    #[test]
+    fn pal_round_trips()
+    {
+        assert_round_trip("pal.asm");
+    }
+}
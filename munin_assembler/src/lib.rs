@@ -1,16 +1,51 @@
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader, BufWriter, Write},
-    path::Path,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
     collections::HashMap,
 };
 
+use munin_device::{
+    decode_program,
+    Bytecode,
+};
+
+mod disassembler;
+mod optimizer;
+mod preprocessor;
+
+pub use disassembler::Disassembler;
+pub use optimizer::optimize_program;
+pub use preprocessor::{
+    Location,
+    Macro,
+};
 
 pub struct Assembler
 {
     pub jump_points:    HashMap<String, usize>,
     pub program_lines:  Vec<String>,
+    pub locations:      Vec<Location>,
+    pub macros:         HashMap<String, Macro>,
+    pub included_files: Vec<PathBuf>,
     pub line_number:    usize,
+    pub temporaries:    Vec<String>,
+    pub optimize:       bool,
+    // source location of each emitted instruction, empty once optimized
+    pub sources:        Vec<String>,
+    // None when the program names variables through an index
+    first_temporary:    Option<usize>,
+    macro_expansions:   usize,
+}
+
+const MAX_NUM_TOKENS: usize = 8;
+const EXPRESSION_OPERATORS: [&str; 7] = ["+", "-", "*", "/", "%", "<<", ">>"];
+
+enum Term<'a>
+{
+    Operand(&'a str),
+    LengthOf(&'a str),
+    BitOf(&'a str, &'a str),
 }
 
 impl Assembler
@@ -21,188 +56,602 @@ impl Assembler
         {
             jump_points:    HashMap::new(),
             program_lines:  Vec::new(),
+            locations:      Vec::new(),
+            macros:         HashMap::new(),
+            included_files: Vec::new(),
             line_number:    0,
-            
+            temporaries:    Vec::new(),
+            optimize:       false,
+            sources:        Vec::new(),
+            first_temporary: None,
+            macro_expansions: 0,
         }
     }
 
     pub fn load_file(&mut self, file_path: impl AsRef<Path>)
     {
-        let file: File = File::open(file_path).expect("Could not open file");
-        let io_file_lines:io::Result<Vec<String>> = BufReader::new(file).lines().collect();
-        let program_lines = io_file_lines.expect("Could not read program lines");
-        self.program_lines = program_lines.into_iter().filter(|x| !x.starts_with(";")).collect::<Vec<String>>().clone();
+        self.program_lines = Vec::new();
+        self.locations = Vec::new();
+        self.included_files = Vec::new();
+        self.macros = HashMap::new();
+        self.macro_expansions = 0;
+
+        self.load_source(file_path.as_ref(), &mut Vec::new());
+
+        self.expand_macros();
     }
 
     pub fn compile_program(&mut self, path: &str)
     {
+        let asm_lines: Vec<String> = self.assemble();
 
         let f = File::create(path).expect("unable to create file");
         let mut f = BufWriter::new(f);
 
-        const MAX_NUM_TOKENS: usize = 8;
+        for asm_line in asm_lines
+        {
+            write!(f, "{}\n", asm_line).expect("unable to write");
+        }
+    }
+
+    pub fn compile_bytecode(&mut self, path: &str)
+    {
+        let bytecode: Bytecode = self.assemble_bytecode();
+
+        fs::write(path, bytecode.encode()).expect("unable to write");
+    }
+
+    pub fn assemble_bytecode(&mut self) -> Bytecode
+    {
+        let asm_lines: Vec<String> = self.assemble()
+            .into_iter()
+            .filter(|line| !line.starts_with(";"))
+            .collect::<Vec<String>>();
+
+        let mut bytecode: Bytecode = Bytecode::from_program(&decode_program(&asm_lines));
+
+        // optimizing renumbers lines, so labels and sources no longer apply
+        if !self.optimize
+        {
+            let mut labels: Vec<(String, usize)> = self.jump_points.iter()
+                .map(|(name, line)| (name.clone(), *line))
+                .collect::<Vec<(String, usize)>>();
+            labels.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+
+            bytecode.labels = labels;
+            bytecode.debug = self.sources.clone();
+        }
+
+        return bytecode;
+    }
+
+    pub fn assemble(&mut self) -> Vec<String>
+    {
+        self.jump_points = HashMap::new();
+        self.temporaries = Vec::new();
+        self.sources = Vec::new();
+        self.first_temporary = Self::first_free_variable(&self.program_lines);
+
+        let mut translated_lines: Vec<Vec<String>> = Vec::new();
 
         for line_number in 0..self.program_lines.len()
         {
-            let line = &self.program_lines.get(line_number).unwrap();
+            let line: String = self.program_lines[line_number].clone();
+            self.line_number = line_number;
 
-            let mut tokens: Vec<&str> = line.split_whitespace()
+            // errors name the source line and, for macro bodies, the call site
+            let context: String = format!("{} (at {})", line, self.location_of(line_number));
+            translated_lines.push(self.translate_line(&line, &context));
+        }
+
+        // `jon` skips a single instruction, so a guarded statement that lowers
+        // to several, or a macro call that expands to several lines, is jumped
+        // over as a whole instead
+        let mut skip_targets: HashMap<usize, String> = HashMap::new();
+        for guard_line in (0..translated_lines.len().saturating_sub(1)).rev()
+        {
+            let guard: Vec<&str> = self.program_lines[guard_line].split_whitespace()
                 .collect::<Vec<&str>>();
-    
-            // push empty values to ensure have tokens
-            for _ in 0..MAX_NUM_TOKENS
+
+            if guard.first() != Some(&"skip-next-if")
             {
-                tokens.push("");
+                continue;
             }
 
-            match tokens[0]
+            let statement_end: usize = self.statement_end(guard_line);
+            let statement_length: usize = translated_lines[guard_line + 1..statement_end].iter()
+                .map(|asm_lines| asm_lines.len())
+                .sum();
+
+            if statement_length < 2
             {
-                "label" =>
-                {
-                    let name = tokens[1];
-                    self.jump_points.insert(name.to_owned(), line_number);
-                }
-                _other =>{}
+                continue;
+            }
+
+            // labels are single tokens, so a name with a space cannot be one
+            let name: String = format!("skip {}", guard_line + 1);
+            let condition: &str = Self::condition_code(guard.get(1).copied().unwrap_or(""), "");
+
+            translated_lines[guard_line] = match Self::inverse_condition(condition)
+            {
+                Some(inverse) => vec![format!("jon {inverse}"), format!("jmp {name}")],
+                None => vec![format!("jmp {name}")],
             };
+            skip_targets.insert(statement_end - 1, name);
         }
 
-        for line_number in 0..self.program_lines.len()
+        let mut asm_lines: Vec<String> = Vec::new();
+        let mut asm_line_number: usize = 0;
+        let mut skip_points: HashMap<String, usize> = HashMap::new();
+
+        // temporaries are ordinary variables; create every variable up to the
+        // last temporary so they can be written in any order
+        if let (false, Some(first_temporary)) = (self.temporaries.is_empty(), self.first_temporary)
         {
-            let line = &self.program_lines.get(line_number).unwrap();
+            asm_lines.push(format!("; expression temporaries: {}", self.temporaries.join(" ")));
+            for variable_index in 0..(first_temporary + self.temporaries.len())
+            {
+                asm_lines.push(format!("set v{variable_index} 0"));
+                self.sources.push("expression temporaries".to_string());
+                asm_line_number += 1;
+            }
+        }
 
-            let mut tokens: Vec<&str> = line.split_whitespace()
+        for line_number in 0..self.program_lines.len()
+        {
+            let tokens: Vec<&str> = self.program_lines[line_number].split_whitespace()
                 .collect::<Vec<&str>>();
-    
-            // push empty values to ensure have tokens
-            for _ in 0..MAX_NUM_TOKENS
+
+            if tokens.first() == Some(&"label")
             {
-                tokens.push("");
+                let name: &str = tokens.get(1).copied().unwrap_or("");
+                self.jump_points.insert(name.to_owned(), asm_line_number);
             }
-    
-            let asm_code: String = match tokens[0]
+
+            asm_line_number += translated_lines[line_number].len();
+
+            if let Some(name) = skip_targets.get(&line_number)
             {
-                ""=>{
-                    "non".to_string()
-                }
-                "label" =>
-                {
-                    "non".to_string()
-                }
-                "set" =>
-                {
-                    let destination = tokens[1];
-                    let source = tokens[3];
-                    match source {
-                        "length-of" =>
-                        {
-                            let true_source = tokens[4];
-                            format!("stl {destination} {true_source}")
-                        }
-                        "bit" =>
-                        {
-                            let bit = tokens[4];
-                            let true_source = tokens[6];
-                            format!("stnb {destination} {true_source} {bit}")
-                        }
-                        _other =>
-                        {
-                            format!("set {destination} {source}")
-                        }
-                    }
-                }
-                "int-add" =>
-                {
-                    let destination = tokens[3];
-                    let source = tokens[1];
-                    format!("iadd {destination} {source}")
-                }
-                "int-subtract" =>
-                { 
-                    let destination = tokens[3];
-                    let source = tokens[1];
-                    format!("isub {destination} {source}") 
-                }
-                "bit-add" =>
+                skip_points.insert(name.clone(), asm_line_number);
+            }
+        }
+
+        for (line_number, translated_line) in translated_lines.into_iter().enumerate()
+        {
+            for asm_line in translated_line
+            {
+                self.sources.push(self.location_of(line_number));
+
+                // go-to is translated with the label name; resolve it now that
+                // every label has a line number
+                match asm_line.strip_prefix("jmp ")
                 {
-                    let destination = tokens[3];
-                    let source = tokens[1];
-                    let carry = tokens[4];
-                    match carry {
-                        "with-carry" =>
-                        {
-                            format!("badc {destination} {source}")
-                        }
-                        _other =>
-                        {
-                            format!("badd {destination} {source}")
-                        }
+                    Some(name) =>
+                    {
+                        let jump_point: &usize = self.jump_points.get(name)
+                            .or_else(|| skip_points.get(name))
+                            .unwrap_or_else(|| panic!("Unknown label {} at {}", name, self.location_of(line_number)));
+                        asm_lines.push(format!("jmp {jump_point}"));
                     }
-                }
-                "bit-subtract" =>
-                {
-                    let destination = tokens[3];
-                    let source = tokens[1];
-                    let borrow = tokens[4];
-                    match borrow {
-                        "with-borrow" =>
-                        {
-                            format!("bsbu {destination} {source}")
-                        }
-                        _other =>
-                        {
-                            format!("bsub {destination} {source}")
-                        }
+                    None =>
+                    {
+                        asm_lines.push(asm_line);
                     }
                 }
-                "shift"=>
+            }
+        }
+
+        asm_lines.push("end".to_string());
+        self.sources.push("end of program".to_string());
+
+        if self.optimize
+        {
+            self.sources = Vec::new();
+            return optimize_program(&asm_lines);
+        }
+
+        return asm_lines;
+    }
+
+    fn location_of(&self, line_number: usize) -> String
+    {
+        return match self.locations.get(line_number)
+        {
+            Some(location) => location.to_string(),
+            None => format!("line {}", line_number + 1),
+        };
+    }
+
+    // the line after the statement guarded by the skip-next-if at guard_line;
+    // the lines of one macro call share the call site in the guard's context
+    fn statement_end(&self, guard_line: usize) -> usize
+    {
+        let guard_context = self.locations.get(guard_line).map(|location| &location.expanded_from);
+        let call_site = |line_number: usize| -> Option<&Location>
+        {
+            let mut location: &Location = self.locations.get(line_number)?;
+            while Some(&location.expanded_from) != guard_context
+            {
+                location = &location.expanded_from.as_ref()?.1;
+            }
+            return Some(location);
+        };
+
+        let statement: Option<&Location> = call_site(guard_line + 1);
+        let mut end: usize = guard_line + 2;
+        while statement.is_some() && end < self.program_lines.len() && call_site(end) == statement
+        {
+            end += 1;
+        }
+
+        return end;
+    }
+
+    // the variable after the highest one the program names; a `v[vK]` may
+    // name any variable, so then there is none
+    fn first_free_variable(program_lines: &[String]) -> Option<usize>
+    {
+        let tokens = || program_lines.iter().flat_map(|line| line.split_whitespace());
+
+        if tokens().any(|token| token.starts_with("v["))
+        {
+            return None;
+        }
+
+        return Some(tokens()
+            .filter_map(|token| token.strip_prefix("v"))
+            .filter_map(|index| index.parse::<usize>().ok())
+            .max()
+            .map_or(0, |index| index + 1));
+    }
+
+    fn temporary(&mut self, index: usize, line: &str) -> String
+    {
+        let first_temporary: usize = self.first_temporary
+            .unwrap_or_else(|| panic!("Expression needs a temporary variable, which could alias an indexed variable: {}", line));
+        let name: String = format!("v{}", first_temporary + index);
+
+        if !self.temporaries.contains(&name)
+        {
+            self.temporaries.push(name.clone());
+        }
+
+        return name;
+    }
+
+    fn parse_term<'a>(tokens: &[&'a str], line: &str) -> (Term<'a>, usize)
+    {
+        match tokens.first().copied()
+        {
+            Some("length-of") =>
+            {
+                match tokens.get(1)
                 {
-                    let destination = tokens[1];
-                    let direction = tokens[2].chars().next().unwrap();
-                    let by = tokens[4];
-                    format!("bs{direction} {destination} {by}")
+                    Some(source) => (Term::LengthOf(source), 2),
+                    None => panic!("Invalid syntax: {}", line),
                 }
-                "go-to" =>
+            }
+            Some("bit") =>
+            {
+                match (tokens.get(1), tokens.get(2), tokens.get(3))
                 {
-                    let line_number: &usize = self.jump_points.get(tokens[1]).unwrap();
-                    format!("jmp {line_number}")
+                    (Some(bit), Some(&"of"), Some(source)) => (Term::BitOf(bit, source), 4),
+                    _other => panic!("Invalid syntax: {}", line),
                 }
-                "compare" =>
+            }
+            Some(operand) if !EXPRESSION_OPERATORS.contains(&operand) =>
+            {
+                (Term::Operand(operand), 1)
+            }
+            _other => panic!("Invalid syntax: {}", line),
+        }
+    }
+
+    fn load_term(destination: &str, term: &Term) -> String
+    {
+        match term
+        {
+            Term::Operand(source) => format!("set {destination} {source}"),
+            Term::LengthOf(source) => format!("stl {destination} {source}"),
+            Term::BitOf(bit, source) => format!("stnb {destination} {source} {bit}"),
+        }
+    }
+
+    fn lower_expression(&mut self, destination: &str, expression: &[&str], line: &str) -> Vec<String>
+    {
+        let (first_term, mut position) = Self::parse_term(expression, line);
+        let mut operations: Vec<(&str, Term)> = Vec::new();
+
+        while position < expression.len()
+        {
+            let operator: &str = expression[position];
+            let asm_operator: &str = match operator
+            {
+                "+" => "iadd",
+                "-" => "isub",
+                "*" => "imul",
+                "/" => "idiv",
+                "%" => "imod",
+                "<<" => "bsl",
+                ">>" => "bsr",
+                _other => panic!("Invalid operator {} in: {}", operator, line),
+            };
+            let (term, term_length) = Self::parse_term(&expression[position + 1..], line);
+            operations.push((asm_operator, term));
+            position += term_length + 1;
+        }
+
+        // the destination can only be built in place if no later term reads it
+        let reads_destination: bool = operations.iter().any(|(_, term)| match term
+        {
+            Term::Operand(source) => *source == destination,
+            Term::LengthOf(source) => *source == destination,
+            Term::BitOf(bit, source) => *bit == destination || *source == destination,
+        });
+
+        let accumulator: String = if reads_destination
+        {
+            self.temporary(0, line)
+        } else
+        {
+            destination.to_string()
+        };
+
+        let mut asm_lines: Vec<String> = vec![Self::load_term(&accumulator, &first_term)];
+
+        for (asm_operator, term) in operations
+        {
+            let source: String = match &term
+            {
+                Term::Operand(source) => source.to_string(),
+                _other =>
                 {
-                    let a = tokens[1];
-                    let b = tokens[3];
-                    format!("cmp {a} {b}")
+                    let term_temporary: String = self.temporary(reads_destination as usize, line);
+                    asm_lines.push(Self::load_term(&term_temporary, &term));
+                    term_temporary
                 }
-                "clear-flags" =>
-                {
-                    "clf".to_string()
+            };
+            asm_lines.push(format!("{asm_operator} {accumulator} {source}"));
+        }
+
+        if accumulator != destination
+        {
+            asm_lines.push(format!("set {destination} {accumulator}"));
+        }
+
+        return asm_lines;
+    }
+
+    fn condition_code(flag: &str, context: &str) -> &'static str
+    {
+        match flag
+        {
+            "equal" => {"e"}
+            "not-equal" => {"ne"}
+            "greater" => {"g"}
+            "greater-or-equal" => {"ge"}
+            "less" => {"l"}
+            "less-or-equal" => {"le"}
+            "carry" => {"c"}
+            "no-carry" => {"nc"}
+            "borrow" => {"b"}
+            "no-borrow" => {"nb"}
+            "" => {""}
+            _other => panic!("Invalid flag {}: {}", flag, context)
+        }
+    }
+
+    // the condition that holds exactly when `condition` does not; the empty
+    // condition always holds, so it has none
+    fn inverse_condition(condition: &str) -> Option<&'static str>
+    {
+        match condition
+        {
+            "e" => Some("ne"),
+            "ne" => Some("e"),
+            "g" => Some("le"),
+            "le" => Some("g"),
+            "ge" => Some("l"),
+            "l" => Some("ge"),
+            "c" => Some("nc"),
+            "nc" => Some("c"),
+            "b" => Some("nb"),
+            "nb" => Some("b"),
+            _other => None,
+        }
+    }
+
+    fn translate_line(&mut self, line: &str, context: &str) -> Vec<String>
+    {
+        let mut tokens: Vec<&str> = line.split_whitespace()
+            .collect::<Vec<&str>>();
+        let expression_length: usize = tokens.len().saturating_sub(3);
+
+        // push empty values to ensure have tokens
+        for _ in 0..MAX_NUM_TOKENS
+        {
+            tokens.push("");
+        }
+
+        let asm_code: String = match tokens[0]
+        {
+            ""=>{
+                "non".to_string()
+            }
+            "label" =>
+            {
+                "non".to_string()
+            }
+            "set" if tokens[1] == "bit" =>
+            {
+                let bit = tokens[2];
+                let destination = tokens[4];
+                let source = tokens[6];
+                format!("snb {destination} {source} {bit}")
+            }
+            "set" if tokens[3] == "flag" =>
+            {
+                let destination = tokens[1];
+                let condition = Self::condition_code(tokens[4], context);
+                format!("stf {destination} {condition}")
+            }
+            "set" =>
+            {
+                let destination = tokens[1];
+                return self.lower_expression(destination, &tokens[3..3 + expression_length], context);
+            }
+            "int-add" =>
+            {
+                let destination = tokens[3];
+                let source = tokens[1];
+                format!("iadd {destination} {source}")
+            }
+            "int-subtract" =>
+            { 
+                let destination = tokens[3];
+                let source = tokens[1];
+                format!("isub {destination} {source}") 
+            }
+            "int-multiply" =>
+            {
+                let destination = tokens[3];
+                let source = tokens[1];
+                format!("imul {destination} {source}")
+            }
+            "int-divide" =>
+            {
+                let destination = tokens[1];
+                let source = tokens[3];
+                format!("idiv {destination} {source}")
+            }
+            "int-modulo" =>
+            {
+                let destination = tokens[1];
+                let source = tokens[3];
+                format!("imod {destination} {source}")
+            }
+            "bit-add" =>
+            {
+                let destination = tokens[3];
+                let source = tokens[1];
+                let carry = tokens[4];
+                match carry {
+                    "with-carry" =>
+                    {
+                        format!("badc {destination} {source}")
+                    }
+                    _other =>
+                    {
+                        format!("badd {destination} {source}")
+                    }
                 }
-                "skip-next-if" =>
-                {
-                    let flag = match tokens[1]
+            }
+            "bit-subtract" =>
+            {
+                let destination = tokens[3];
+                let source = tokens[1];
+                let borrow = tokens[4];
+                match borrow {
+                    "with-borrow" =>
+                    {
+                        format!("bsbu {destination} {source}")
+                    }
+                    _other =>
                     {
-                        "equal" => {"e"}
-                        "not-equal" => {"ne"}
-                        "greater" => {"g"}
-                        "greater-or-equal" => {"ge"}
-                        "less" => {"l"}
-                        "less-or-equal" => {"le"}
-                        "carry" => {"c"}
-                        "no-carry" => {"nc"}
-                        "borrow" => {"b"}
-                        "no-borrow" => {"nb"}
-                        "" => {""}
-                        _other => panic!("Invalid flag: {}", tokens[1])
-                    };
-                    format!("jon {flag}")
+                        format!("bsub {destination} {source}")
+                    }
                 }
-                _other =>
+            }
+            "shift"=>
+            {
+                let destination = tokens[1];
+                let direction = tokens[2].chars().next().unwrap();
+                let by = tokens[4];
+                format!("bs{direction} {destination} {by}")
+            }
+            "go-to" =>
+            {
+                let name = tokens[1];
+                format!("jmp {name}")
+            }
+            "compare" =>
+            {
+                let a = tokens[1];
+                let b = tokens[3];
+                format!("cmp {a} {b}")
+            }
+            "clear-flags" =>
+            {
+                "clf".to_string()
+            }
+            "end" =>
+            {
+                "end".to_string()
+            }
+            "accept" =>
+            {
+                "acc".to_string()
+            }
+            "reject" =>
+            {
+                "rej".to_string()
+            }
+            "output" =>
+            {
+                let source = tokens[1];
+                format!("out {source}")
+            }
+            "guess" =>
+            {
+                let destination = tokens[1];
+                format!("guess {destination}")
+            }
+            "random" =>
+            {
+                let destination = tokens[1];
+                format!("rand {destination}")
+            }
+            "bitwise-and" | "bitwise-or" | "bitwise-xor" =>
+            {
+                let operator = tokens[0].trim_start_matches("bitwise-");
+                let destination = tokens[3];
+                let source = tokens[1];
+                format!("{operator} {destination} {source}")
+            }
+            "bitwise-not" =>
+            {
+                let destination = tokens[1];
+                format!("not {destination}")
+            }
+            "move-head-left" | "move-head-right" | "rewind-head" =>
+            {
+                let operator = match tokens[0]
                 {
-                    panic!("Invalid syntax: {}", line);
-                }
-            };
-            write!(f, "{}\n", asm_code).expect("unable to write");
-        }
-        write!(f, "end\n").expect("unable to write");
+                    "move-head-left" => "left",
+                    "move-head-right" => "right",
+                    _rewind => "rew",
+                };
+                let input = tokens[1];
+                format!("{operator} {input}")
+            }
+            "read-head" =>
+            {
+                let destination = tokens[3];
+                let input = tokens[1];
+                format!("read {destination} {input}")
+            }
+            "skip-next-if" =>
+            {
+                let flag = Self::condition_code(tokens[1], context);
+                format!("jon {flag}")
+            }
+            _other =>
+            {
+                panic!("Invalid syntax: {}", context);
+            }
+        };
+
+        return vec![asm_code];
     }
 
 }
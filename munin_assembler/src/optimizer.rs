@@ -0,0 +1,287 @@
+use std::collections::BTreeSet;
+
+use munin_device::{
+    decode_program,
+    Instruction,
+    Operand,
+};
+
+fn is_after_skip(program: &[Instruction], line: usize) -> bool
+{
+    return line > 0 && program[line - 1].operator == "jon";
+}
+
+fn jump_targets(program: &[Instruction]) -> BTreeSet<usize>
+{
+    return program.iter()
+        .filter_map(|instruction| instruction.jump_target())
+        .collect::<BTreeSet<usize>>();
+}
+
+// only values every arithmetic mode agrees on are folded
+fn immediate(operand: &str) -> Option<u32>
+{
+    return match Operand::parse(operand)
+    {
+        Some(Operand::Immediate(value)) => u32::try_from(value).ok(),
+        _other => None,
+    };
+}
+
+// drops the lines not kept and points every jump at the first kept line at or
+// after its old target
+fn remove_lines(program: &[Instruction], keep: &[bool]) -> Vec<Instruction>
+{
+    let mut new_index: Vec<usize> = Vec::with_capacity(program.len() + 1);
+    let mut kept: usize = 0;
+
+    for line_kept in keep.iter()
+    {
+        new_index.push(kept);
+        kept += *line_kept as usize;
+    }
+    new_index.push(kept);
+
+    return program.iter()
+        .zip(keep.iter())
+        .filter(|(_, keep)| **keep)
+        .map(|(instruction, _)| match instruction.jump_target()
+        {
+            Some(target) => Instruction
+            {
+                operator:   "jmp".to_string(),
+                operands:   vec![new_index[target.min(program.len())].to_string()],
+            },
+            None => instruction.clone(),
+        })
+        .collect::<Vec<Instruction>>();
+}
+
+fn thread_jumps(program: &mut [Instruction]) -> bool
+{
+    let mut changed: bool = false;
+
+    for line in 0..program.len()
+    {
+        let mut target: usize = match program[line].jump_target()
+        {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let mut visited: BTreeSet<usize> = BTreeSet::from([line]);
+
+        while let Some(next) = program.get(target).and_then(|instruction| instruction.jump_target())
+        {
+            // a loop made only of jumps is left alone
+            if !visited.insert(target)
+            {
+                target = program[line].jump_target().unwrap_or(target);
+                break;
+            }
+            target = next;
+        }
+
+        // a jump straight to `end`, `acc` or `rej` may as well halt there
+        if let Some(halt) = program.get(target).filter(|instruction| instruction.successors(target).is_empty())
+        {
+            program[line] = halt.clone();
+            changed = true;
+        }
+        else if Some(target) != program[line].jump_target()
+        {
+            program[line] = Instruction::parse(&format!("jmp {target}"));
+            changed = true;
+        }
+    }
+
+    return changed;
+}
+
+fn fold_constants(program: &mut [Instruction], keep: &mut [bool]) -> bool
+{
+    let targets: BTreeSet<usize> = jump_targets(program);
+    let mut changed: bool = false;
+
+    for line in 0..program.len().saturating_sub(1)
+    {
+        if !keep[line] || !keep[line + 1] || targets.contains(&(line + 1)) || is_after_skip(program, line)
+        {
+            continue;
+        }
+
+        let (set, add) = (&program[line], &program[line + 1]);
+        if set.operator != "set" || add.operator != "iadd" || set.operand(0) != add.operand(0)
+        {
+            continue;
+        }
+
+        // only `iadd` folds: the sum is never narrower than the first value,
+        // so the destination's maximum size is unchanged
+        if let (Some(first), Some(second)) = (immediate(set.operand(1)), immediate(add.operand(1)))
+        {
+            if let Some(sum) = first.checked_add(second)
+            {
+                program[line] = Instruction::parse(&format!("set {} {}", set.operand(0), sum));
+                keep[line + 1] = false;
+                changed = true;
+            }
+        }
+    }
+
+    return changed;
+}
+
+fn remove_redundant(program: &[Instruction], keep: &mut [bool]) -> bool
+{
+    let targets: BTreeSet<usize> = jump_targets(program);
+    let mut changed: bool = false;
+
+    for line in 0..program.len()
+    {
+        let instruction: &Instruction = &program[line];
+        let after_skip: bool = is_after_skip(program, line);
+
+        let redundant: bool = match instruction.operator.as_str()
+        {
+            "non" => true,
+            "clf" =>
+            {
+                line > 0 && keep[line - 1] && program[line - 1].operator == "clf"
+                    && !is_after_skip(program, line - 1) && !targets.contains(&line)
+            }
+            "jmp" => instruction.jump_target() == Some(line + 1),
+            _other => false,
+        };
+
+        if !redundant || !keep[line]
+        {
+            continue;
+        }
+
+        if !after_skip
+        {
+            keep[line] = false;
+            changed = true;
+        }
+        // a skip over padding does nothing, so both go; unless a skip before
+        // it depends on the line count
+        else if instruction.operator == "non" && keep[line - 1] && !is_after_skip(program, line - 1)
+        {
+            keep[line - 1] = false;
+            keep[line] = false;
+            changed = true;
+        }
+    }
+
+    return changed;
+}
+
+fn remove_unreachable(program: &[Instruction], keep: &mut [bool]) -> bool
+{
+    let mut reachable: Vec<bool> = vec![false; program.len()];
+    let mut pending: Vec<usize> = if program.is_empty() { Vec::new() } else { vec![0] };
+
+    while let Some(line) = pending.pop()
+    {
+        if line >= program.len() || reachable[line]
+        {
+            continue;
+        }
+        reachable[line] = true;
+        pending.extend(program[line].successors(line));
+    }
+
+    let mut changed: bool = false;
+
+    for line in 0..program.len()
+    {
+        if !reachable[line] && keep[line]
+        {
+            keep[line] = false;
+            changed = true;
+        }
+    }
+
+    return changed;
+}
+
+pub fn optimize_program(asm_lines: &[String]) -> Vec<String>
+{
+    let comments: Vec<String> = asm_lines.iter()
+        .filter(|line| line.starts_with(";"))
+        .cloned()
+        .collect::<Vec<String>>();
+    let instructions: Vec<String> = asm_lines.iter()
+        .filter(|line| !line.starts_with(";"))
+        .cloned()
+        .collect::<Vec<String>>();
+
+    let mut program: Vec<Instruction> = decode_program(&instructions);
+
+    loop
+    {
+        let mut changed: bool = thread_jumps(&mut program);
+
+        // one kind of removal per round so that every decision sees the
+        // program as it is
+        let mut keep: Vec<bool> = vec![true; program.len()];
+        changed |= remove_unreachable(&program, &mut keep)
+            || fold_constants(&mut program, &mut keep)
+            || remove_redundant(&program, &mut keep);
+
+        program = remove_lines(&program, &keep);
+
+        if !changed
+        {
+            break;
+        }
+    }
+
+    return comments.into_iter()
+        .chain(program.iter().map(|instruction| instruction.to_string()))
+        .collect::<Vec<String>>();
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String>
+    {
+        return text.iter().map(|line| line.to_string()).collect::<Vec<String>>();
+    }
+
+    #[test]
+    fn remove_lines_renumbers_jumps()
+    {
+        let program: Vec<Instruction> = decode_program(&lines(&["jmp 3", "non", "non", "set v0 1", "jmp 1", "jmp 6"]));
+        let keep: [bool; 6] = [true, false, false, true, true, true];
+
+        let program: Vec<String> = remove_lines(&program, &keep).iter()
+            .map(|instruction| instruction.to_string())
+            .collect::<Vec<String>>();
+
+        // a removed target moves to the next kept line, and one past the end
+        // stays past the end
+        assert_eq!(program, lines(&["jmp 1", "set v0 1", "jmp 1", "jmp 4"]));
+    }
+
+    #[test]
+    fn removes_a_skip_over_padding()
+    {
+        let optimized: Vec<String> = optimize_program(&lines(&["set v0 1", "cmp v0 1", "jon e", "non", "iadd v0 1", "end"]));
+
+        assert_eq!(optimized, lines(&["set v0 1", "cmp v0 1", "iadd v0 1", "end"]));
+    }
+
+    #[test]
+    fn folds_set_and_iadd_of_immediates()
+    {
+        let optimized: Vec<String> = optimize_program(&lines(&["set v0 2", "iadd v0 3", "set v1 -1", "iadd v1 1", "end"]));
+
+        // -1 is not a value every mode agrees on, so it is left alone
+        assert_eq!(optimized, lines(&["set v0 5", "set v1 -1", "iadd v1 1", "end"]));
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::Assembler;
+
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location
+{
+    pub file:           String,
+    pub line:           usize,
+    pub expanded_from:  Option<(String, Box<Location>)>,
+}
+
+impl Location
+{
+    pub fn new(file: &str, line: usize) -> Self
+    {
+        Self
+        {
+            file:           file.to_string(),
+            line,
+            expanded_from:  None,
+        }
+    }
+}
+
+impl fmt::Display for Location
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}:{}", self.file, self.line)?;
+
+        if let Some((macro_name, call_site)) = &self.expanded_from
+        {
+            write!(f, " in macro `{}` expanded at {}", macro_name, call_site)?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Macro
+{
+    pub parameters:     Vec<String>,
+    pub body:           Vec<String>,
+    pub body_locations: Vec<Location>,
+    pub labels:         Vec<String>,
+}
+
+impl Assembler
+{
+    pub(crate) fn load_source(&mut self, file_path: &Path, include_stack: &mut Vec<PathBuf>)
+    {
+        let canonical_path: PathBuf = file_path.canonicalize()
+            .unwrap_or_else(|_| panic!("Could not open file: {}", file_path.display()));
+
+        if include_stack.contains(&canonical_path)
+        {
+            let cycle: Vec<String> = include_stack.iter()
+                .chain(std::iter::once(&canonical_path))
+                .map(|path| path.display().to_string())
+                .collect::<Vec<String>>();
+            panic!("Include cycle: {}", cycle.join(" -> "));
+        }
+
+        // include-once: a file already merged is skipped
+        if self.included_files.contains(&canonical_path)
+        {
+            return;
+        }
+
+        let namespace: String = Self::namespace_of(&canonical_path);
+        if let Some(other) = self.included_files.iter().find(|other| Self::namespace_of(other) == namespace)
+        {
+            panic!("Included files {} and {} share the label namespace `{}`", other.display(), canonical_path.display(), namespace);
+        }
+
+        self.included_files.push(canonical_path.clone());
+        include_stack.push(canonical_path.clone());
+
+        let file_name: String = file_path.display().to_string();
+        let file: File = File::open(file_path).expect("Could not open file");
+        let io_file_lines:io::Result<Vec<String>> = BufReader::new(file).lines().collect();
+        let program_lines = io_file_lines.expect("Could not read program lines");
+
+        for (line_index, line) in program_lines.into_iter().enumerate()
+        {
+            if line.starts_with(";")
+            {
+                continue;
+            }
+
+            let location: Location = Location::new(&file_name, line_index + 1);
+
+            if let Some(included) = line.trim().strip_prefix("include ")
+            {
+                let included: &str = included.trim();
+                if included.len() < 2 || !included.starts_with('"') || !included.ends_with('"')
+                {
+                    panic!("Invalid include at {}: {}", location, line);
+                }
+
+                let included_path: PathBuf = file_path.parent()
+                    .unwrap_or(Path::new(""))
+                    .join(&included[1..included.len() - 1]);
+
+                if !included_path.exists()
+                {
+                    panic!("Included file {} not found at {}", included_path.display(), location);
+                }
+
+                self.load_source(&included_path, include_stack);
+                continue;
+            }
+
+            self.program_lines.push(Self::qualify_labels(&line, &namespace));
+            self.locations.push(location);
+        }
+
+        include_stack.pop();
+    }
+
+    fn namespace_of(path: &Path) -> String
+    {
+        return path.file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+    }
+
+    // labels are local to their file unless named as `file::label`
+    fn qualify_labels(line: &str, namespace: &str) -> String
+    {
+        let tokens: Vec<&str> = line.split_whitespace().collect::<Vec<&str>>();
+
+        match tokens.as_slice()
+        {
+            ["label", name, rest @ ..] | ["go-to", name, rest @ ..] if !name.contains("::") =>
+            {
+                let mut qualified: Vec<String> = vec![tokens[0].to_string(), format!("{}::{}", namespace, name)];
+                qualified.extend(rest.iter().map(|token| token.to_string()));
+                return qualified.join(" ");
+            }
+            _other =>
+            {
+                return line.to_string();
+            }
+        }
+    }
+
+    // splits `name(a, b)` into its name and arguments
+    fn parse_signature(text: &str) -> Option<(String, Vec<String>)>
+    {
+        let text: &str = text.trim();
+        let open: usize = text.find('(')?;
+
+        if !text.ends_with(')')
+        {
+            return None;
+        }
+
+        let name: String = text[..open].trim().to_string();
+        let arguments: Vec<String> = text[open + 1..text.len() - 1]
+            .split(',')
+            .map(|argument| argument.trim().to_string())
+            .filter(|argument| !argument.is_empty())
+            .collect::<Vec<String>>();
+
+        if name.is_empty() || name.contains(char::is_whitespace)
+        {
+            return None;
+        }
+
+        return Some((name, arguments));
+    }
+
+    pub(crate) fn expand_macros(&mut self)
+    {
+        let mut lines: Vec<String> = Vec::new();
+        let mut locations: Vec<Location> = Vec::new();
+        let mut definition: Option<(String, Macro, Location)> = None;
+
+        for (line, location) in self.program_lines.iter().zip(self.locations.iter())
+        {
+            let trimmed: &str = line.trim();
+
+            if let Some(signature) = trimmed.strip_prefix("macro ")
+            {
+                if let Some((name, _, start)) = &definition
+                {
+                    panic!("Nested macro definition at {}: macro `{}` started at {} is not closed", location, name, start);
+                }
+
+                let (name, parameters) = Self::parse_signature(signature)
+                    .unwrap_or_else(|| panic!("Invalid macro definition at {}: {}", location, line));
+
+                if self.macros.contains_key(&name)
+                {
+                    panic!("Duplicate macro definition at {}: {}", location, name);
+                }
+
+                let new_macro: Macro = Macro
+                {
+                    parameters,
+                    body:           Vec::new(),
+                    body_locations: Vec::new(),
+                    labels:         Vec::new(),
+                };
+                definition = Some((name, new_macro, location.clone()));
+            }
+            else if trimmed == "end-macro"
+            {
+                match definition.take()
+                {
+                    Some((name, new_macro, _)) =>
+                    {
+                        self.macros.insert(name, new_macro);
+                    }
+                    None => panic!("end-macro without macro at {}", location),
+                }
+            }
+            else if let Some((_, new_macro, _)) = &mut definition
+            {
+                let tokens: Vec<&str> = trimmed.split_whitespace().collect::<Vec<&str>>();
+                if tokens.len() > 1 && tokens[0] == "label"
+                {
+                    new_macro.labels.push(tokens[1].to_string());
+                }
+                new_macro.body.push(line.clone());
+                new_macro.body_locations.push(location.clone());
+            }
+            else
+            {
+                lines.push(line.clone());
+                locations.push(location.clone());
+            }
+        }
+
+        if let Some((name, _, start)) = definition
+        {
+            panic!("Unterminated macro `{}` started at {}", name, start);
+        }
+
+        self.program_lines = Vec::new();
+        self.locations = Vec::new();
+
+        for (line, location) in lines.into_iter().zip(locations)
+        {
+            self.expand_line(line, location, &mut Vec::new());
+        }
+    }
+
+    fn expand_line(&mut self, line: String, location: Location, expansion_stack: &mut Vec<String>)
+    {
+        let call: Option<(String, Vec<String>)> = Self::parse_signature(&line)
+            .filter(|(name, _)| self.macros.contains_key(name));
+
+        let (name, arguments) = match call
+        {
+            Some(call) => call,
+            None =>
+            {
+                self.program_lines.push(line);
+                self.locations.push(location);
+                return;
+            }
+        };
+
+        if expansion_stack.contains(&name) || expansion_stack.len() >= MAX_EXPANSION_DEPTH
+        {
+            panic!("Recursive expansion of macro `{}` at {}", name, location);
+        }
+
+        let called_macro: Macro = self.macros[&name].clone();
+
+        if arguments.len() != called_macro.parameters.len()
+        {
+            panic!(
+                "Macro `{}` expects {} arguments but {} were given at {}",
+                name, called_macro.parameters.len(), arguments.len(), location
+            );
+        }
+
+        // every expansion gets its own copy of the labels declared in the body
+        self.macro_expansions += 1;
+        let mut substitutions: HashMap<&str, String> = HashMap::new();
+        for (parameter, argument) in called_macro.parameters.iter().zip(arguments.iter())
+        {
+            substitutions.insert(parameter, argument.clone());
+        }
+        for label in called_macro.labels.iter()
+        {
+            substitutions.insert(label, format!("{}.{}.{}", name, self.macro_expansions, label));
+        }
+
+        expansion_stack.push(name.clone());
+
+        for (body_line, body_location) in called_macro.body.iter().zip(called_macro.body_locations.iter())
+        {
+            let expanded_line: String = body_line.split_whitespace()
+                .map(|token| substitutions.get(token).cloned().unwrap_or(token.to_string()))
+                .collect::<Vec<String>>()
+                .join(" ");
+
+            // arguments may themselves be passed on to nested calls
+            let expanded_line: String = match Self::parse_signature(&expanded_line)
+            {
+                Some((inner_name, inner_arguments)) if self.macros.contains_key(&inner_name) =>
+                {
+                    let inner_arguments: Vec<String> = inner_arguments.iter()
+                        .map(|argument| substitutions.get(argument.as_str()).cloned().unwrap_or(argument.clone()))
+                        .collect::<Vec<String>>();
+                    format!("{}({})", inner_name, inner_arguments.join(", "))
+                }
+                _other => expanded_line,
+            };
+
+            let mut expanded_location: Location = body_location.clone();
+            expanded_location.expanded_from = Some((name.clone(), Box::new(location.clone())));
+
+            self.expand_line(expanded_line, expanded_location, expansion_stack);
+        }
+
+        expansion_stack.pop();
+    }
+}
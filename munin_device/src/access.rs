@@ -0,0 +1,85 @@
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+// how execution code may read the inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputAccess
+{
+    // any instruction reads any input bit for free
+    #[default]
+    RandomAccess,
+    // inputs are only read through a head that moves both ways; each head
+    // position is charged as execution space
+    TwoWay,
+    // inputs are only read through a head that moves forwards, and rewinding
+    // starts another pass
+    Streaming,
+}
+
+impl FromStr for InputAccess
+{
+    type Err = String;
+
+    fn from_str(access: &str) -> Result<Self, Self::Err>
+    {
+        match access
+        {
+            "random" => Ok(InputAccess::RandomAccess),
+            "two-way" => Ok(InputAccess::TwoWay),
+            "streaming" => Ok(InputAccess::Streaming),
+            _other => Err(format!("unknown input access `{}`; expected random, two-way or streaming", access)),
+        }
+    }
+}
+
+impl fmt::Display for InputAccess
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            InputAccess::RandomAccess => write!(f, "random"),
+            InputAccess::TwoWay => write!(f, "two-way"),
+            InputAccess::Streaming => write!(f, "streaming"),
+        }
+    }
+}
+
+// a read head on one input; position -1 and the input length are the end
+// markers either side of the bits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Head
+{
+    pub position:   isize,
+    pub used:       bool,
+    pub passes:     usize,
+}
+
+impl Head
+{
+    pub fn new() -> Self
+    {
+        Self
+        {
+            position:   0,
+            used:       false,
+            passes:     0,
+        }
+    }
+
+    // bits needed to hold any position on an input of this length
+    pub fn position_bits(input_length: usize) -> usize
+    {
+        return (input_length + 1).ilog2() as usize + 1;
+    }
+}
+
+impl Default for Head
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+use bit_vec::BitVec;
+
+use crate::fault::Fault;
+
+// how `set`, `cmp` and the integer operators treat values that leave the
+// range of a register, including negative immediates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArithmeticMode
+{
+    // 0 to u32::MAX; leaving the range is a fault
+    #[default]
+    Unsigned,
+    // 0 to 2^width - 1, modulo 2^width
+    Wrapping
+    {
+        width:  usize,
+    },
+    // 0 to u32::MAX, clamped
+    Saturating,
+    // i32::MIN to i32::MAX in two's complement, so every variable and input
+    // holds a sign bit; leaving the range is a fault
+    Signed,
+}
+
+impl ArithmeticMode
+{
+    pub fn is_signed(&self) -> bool
+    {
+        return *self == ArithmeticMode::Signed;
+    }
+
+    pub fn normalize(&self, value: i128, line: usize) -> Result<i64, Fault>
+    {
+        let (min, max): (i128, i128) = match self
+        {
+            ArithmeticMode::Unsigned | ArithmeticMode::Saturating => (0, u32::MAX as i128),
+            ArithmeticMode::Wrapping { width } => (0, (1 << width) - 1),
+            ArithmeticMode::Signed => (i32::MIN as i128, i32::MAX as i128),
+        };
+
+        match self
+        {
+            ArithmeticMode::Wrapping { width } => Ok(value.rem_euclid(1 << width) as i64),
+            ArithmeticMode::Saturating => Ok(value.clamp(min, max) as i64),
+            _other if value < min => Err(Fault::Underflow { line }),
+            _other if value > max => Err(Fault::Overflow { line }),
+            _other => Ok(value as i64),
+        }
+    }
+
+    // shortest representation of a value; signed values keep one sign bit
+    pub fn to_bits(&self, value: i64) -> BitVec
+    {
+        if !self.is_signed()
+        {
+            let num_bits: usize = (value as u64).checked_ilog2().map_or(1, |log| log as usize + 1);
+            return BitVec::from_fn(num_bits, |i| (value >> i) & 1 == 1);
+        }
+
+        let magnitude: i64 = if value < 0 { !value } else { value };
+        let num_bits: usize = magnitude.checked_ilog2().map_or(0, |log| log as usize + 1) + 1;
+
+        return BitVec::from_fn(num_bits, |i| (value >> i) & 1 == 1);
+    }
+
+    pub fn from_bits(&self, bits: &BitVec) -> i64
+    {
+        let mut value: i64 = 0;
+
+        for i in 0..bits.len()
+        {
+            value += (bits[i] as i64) << i;
+        }
+
+        if self.is_signed() && !bits.is_empty() && bits[bits.len() - 1]
+        {
+            value -= 1 << bits.len();
+        }
+
+        return value;
+    }
+}
+
+impl FromStr for ArithmeticMode
+{
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err>
+    {
+        match mode.split_once(':')
+        {
+            Some(("wrapping", width)) => match width.parse::<usize>()
+            {
+                Ok(width) if (1..=32).contains(&width) => Ok(ArithmeticMode::Wrapping { width }),
+                _other => Err(format!("wrapping width must be between 1 and 32, found `{}`", width)),
+            },
+            None if mode == "unsigned" => Ok(ArithmeticMode::Unsigned),
+            None if mode == "saturating" => Ok(ArithmeticMode::Saturating),
+            None if mode == "signed" => Ok(ArithmeticMode::Signed),
+            _other => Err(format!("unknown arithmetic mode `{}`; expected unsigned, wrapping:WIDTH, saturating or signed", mode)),
+        }
+    }
+}
+
+impl fmt::Display for ArithmeticMode
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            ArithmeticMode::Unsigned => write!(f, "unsigned"),
+            ArithmeticMode::Wrapping { width } => write!(f, "wrapping:{}", width),
+            ArithmeticMode::Saturating => write!(f, "saturating"),
+            ArithmeticMode::Signed => write!(f, "signed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const MAX: i128 = u32::MAX as i128;
+
+    #[test]
+    fn unsigned_faults_outside_u32()
+    {
+        let mode: ArithmeticMode = ArithmeticMode::Unsigned;
+
+        assert_eq!(mode.normalize(0, 3), Ok(0));
+        assert_eq!(mode.normalize(MAX, 3), Ok(u32::MAX as i64));
+        assert_eq!(mode.normalize(-1, 3), Err(Fault::Underflow { line: 3 }));
+        assert_eq!(mode.normalize(MAX + 1, 3), Err(Fault::Overflow { line: 3 }));
+    }
+
+    #[test]
+    fn wrapping_takes_values_modulo_the_width()
+    {
+        let mode: ArithmeticMode = ArithmeticMode::Wrapping { width: 4 };
+
+        assert_eq!(mode.normalize(15, 0), Ok(15));
+        assert_eq!(mode.normalize(16, 0), Ok(0));
+        assert_eq!(mode.normalize(-1, 0), Ok(15));
+        assert_eq!(mode.normalize(-16, 0), Ok(0));
+
+        let widest: ArithmeticMode = ArithmeticMode::Wrapping { width: 32 };
+        assert_eq!(widest.normalize(MAX + 1, 0), Ok(0));
+        assert_eq!(widest.normalize(-1, 0), Ok(u32::MAX as i64));
+    }
+
+    #[test]
+    fn saturating_clamps_to_u32()
+    {
+        let mode: ArithmeticMode = ArithmeticMode::Saturating;
+
+        assert_eq!(mode.normalize(-1, 0), Ok(0));
+        assert_eq!(mode.normalize(MAX, 0), Ok(u32::MAX as i64));
+        assert_eq!(mode.normalize(MAX + 1, 0), Ok(u32::MAX as i64));
+    }
+
+    #[test]
+    fn signed_faults_outside_i32()
+    {
+        let mode: ArithmeticMode = ArithmeticMode::Signed;
+
+        assert_eq!(mode.normalize(i32::MIN as i128, 1), Ok(i32::MIN as i64));
+        assert_eq!(mode.normalize(i32::MAX as i128, 1), Ok(i32::MAX as i64));
+        assert_eq!(mode.normalize(i32::MIN as i128 - 1, 1), Err(Fault::Underflow { line: 1 }));
+        assert_eq!(mode.normalize(i32::MAX as i128 + 1, 1), Err(Fault::Overflow { line: 1 }));
+    }
+
+    #[test]
+    fn signed_values_keep_a_sign_bit()
+    {
+        let mode: ArithmeticMode = ArithmeticMode::Signed;
+
+        for (value, num_bits) in [(0, 1), (-1, 1), (5, 4), (-8, 4), (i32::MIN as i64, 32), (i32::MAX as i64, 32)]
+        {
+            let bits: BitVec = mode.to_bits(value);
+            assert_eq!(bits.len(), num_bits, "{}", value);
+            assert_eq!(mode.from_bits(&bits), value);
+        }
+
+        assert_eq!(ArithmeticMode::Unsigned.to_bits(5).len(), 3);
+        assert_eq!(mode.from_bits(&BitVec::new()), 0);
+    }
+}
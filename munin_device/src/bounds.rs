@@ -0,0 +1,425 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+};
+
+use crate::{
+    instruction::{
+        Instruction,
+        Operand,
+    },
+    NUM_FLAGS,
+};
+
+// bit width bound of the form constant + log * log n + linear * n, where n is
+// the length of the longest input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound
+{
+    Finite
+    {
+        constant:   usize,
+        log:        usize,
+        linear:     usize,
+    },
+    Unbounded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SpaceClass
+{
+    Constant,
+    Logarithmic,
+    Linear,
+    Unbounded,
+}
+
+impl fmt::Display for SpaceClass
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            SpaceClass::Constant => write!(f, "O(1)"),
+            SpaceClass::Logarithmic => write!(f, "O(log n)"),
+            SpaceClass::Linear => write!(f, "O(n)"),
+            SpaceClass::Unbounded => write!(f, "unbounded"),
+        }
+    }
+}
+
+fn bits_needed(value: usize) -> usize
+{
+    return value.checked_ilog2().map_or(1, |log| log as usize + 1);
+}
+
+fn ceil_log2(value: usize) -> usize
+{
+    return if value <= 1 { 0 } else { (value - 1).ilog2() as usize + 1 };
+}
+
+impl Bound
+{
+    pub fn constant(constant: usize) -> Self
+    {
+        return Bound::Finite { constant, log: 0, linear: 0 };
+    }
+
+    pub fn input_length() -> Self
+    {
+        return Bound::Finite { constant: 0, log: 0, linear: 1 };
+    }
+
+    pub fn plus(self, other: Bound) -> Bound
+    {
+        match (self, other)
+        {
+            (Bound::Finite { constant: c1, log: l1, linear: n1 }, Bound::Finite { constant: c2, log: l2, linear: n2 }) =>
+            {
+                Bound::Finite { constant: c1 + c2, log: l1 + l2, linear: n1 + n2 }
+            }
+            _other => Bound::Unbounded,
+        }
+    }
+
+    // componentwise, so never below either side
+    pub fn max(self, other: Bound) -> Bound
+    {
+        match (self, other)
+        {
+            (Bound::Finite { constant: c1, log: l1, linear: n1 }, Bound::Finite { constant: c2, log: l2, linear: n2 }) =>
+            {
+                Bound::Finite { constant: c1.max(c2), log: l1.max(l2), linear: n1.max(n2) }
+            }
+            _other => Bound::Unbounded,
+        }
+    }
+
+    pub fn is_within(&self, other: &Bound) -> bool
+    {
+        return self.max(*other) == *other;
+    }
+
+    // width of the length of a value at most this wide
+    pub fn length_of(self) -> Bound
+    {
+        match self
+        {
+            Bound::Finite { constant, log: 0, linear: 0 } => Bound::constant(bits_needed(constant)),
+            Bound::Finite { constant, log, linear } =>
+            {
+                Bound::Finite { constant: ceil_log2(constant + log + linear) + 1, log: 1, linear: 0 }
+            }
+            Bound::Unbounded => Bound::Unbounded,
+        }
+    }
+
+    pub fn evaluate(&self, input_length: usize) -> Option<usize>
+    {
+        match self
+        {
+            Bound::Finite { constant, log, linear } =>
+            {
+                let log_n: usize = input_length.max(1).ilog2() as usize;
+                Some(constant + log * log_n + linear * input_length)
+            }
+            Bound::Unbounded => None,
+        }
+    }
+
+    pub fn class(&self) -> SpaceClass
+    {
+        match self
+        {
+            Bound::Finite { log: 0, linear: 0, .. } => SpaceClass::Constant,
+            Bound::Finite { linear: 0, .. } => SpaceClass::Logarithmic,
+            Bound::Finite { .. } => SpaceClass::Linear,
+            Bound::Unbounded => SpaceClass::Unbounded,
+        }
+    }
+}
+
+impl fmt::Display for Bound
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let (constant, log, linear) = match self
+        {
+            Bound::Finite { constant, log, linear } => (*constant, *log, *linear),
+            Bound::Unbounded => return write!(f, "unbounded"),
+        };
+
+        let mut terms: Vec<String> = Vec::new();
+
+        match linear
+        {
+            0 => {}
+            1 => terms.push("n".to_string()),
+            _other => terms.push(format!("{} n", linear)),
+        }
+        match log
+        {
+            0 => {}
+            1 => terms.push("log n".to_string()),
+            _other => terms.push(format!("{} log n", log)),
+        }
+        if constant > 0 || terms.is_empty()
+        {
+            terms.push(constant.to_string());
+        }
+
+        write!(f, "{}", terms.join(" + "))
+    }
+}
+
+pub struct SpaceEstimate
+{
+    pub variables:  BTreeMap<usize, Bound>,
+    pub num_bits:   usize,
+    pub total:      Bound,
+}
+
+impl SpaceEstimate
+{
+    pub fn class(&self) -> SpaceClass
+    {
+        return self.total.class();
+    }
+}
+
+type State = BTreeMap<usize, Bound>;
+
+fn operand_width(operand: &str, state: &State) -> Bound
+{
+    match Operand::parse(operand)
+    {
+        Some(Operand::Variable(index)) => state.get(&index).copied().unwrap_or(Bound::constant(1)),
+        Some(Operand::Bit(_)) => Bound::constant(1),
+        Some(Operand::Input(_)) => Bound::input_length(),
+        // a negative immediate is seen as its 32-bit two's complement
+        Some(Operand::Immediate(value)) => Bound::constant(bits_needed(value as u32 as usize)),
+        // any variable may be the one read
+        Some(Operand::VariableAt(_)) => state.values().fold(Bound::constant(1), |width, other| width.max(*other)),
+        Some(Operand::BitAt(_)) => Bound::constant(1),
+        Some(Operand::InputAt(_)) => Bound::input_length(),
+        None => Bound::Unbounded,
+    }
+}
+
+// width of the value an instruction writes to its destination
+fn written_width(instruction: &Instruction, state: &State) -> Bound
+{
+    let destination: Bound = operand_width(instruction.operand(0), state);
+    let source: Bound = operand_width(instruction.operand(1), state);
+
+    match instruction.operator.as_str()
+    {
+        "set" => source,
+        "stl" => source.length_of(),
+        "iadd" => destination.max(source).plus(Bound::constant(1)),
+        "isub" | "bsr" | "idiv" | "and" | "not" => destination,
+        "or" | "xor" => destination.max(source),
+        "snb" => match Operand::parse(instruction.operand(2))
+        {
+            Some(Operand::Immediate(bit)) if bit >= 0 => destination.max(Bound::constant(bit as usize + 1)),
+            _other => Bound::Unbounded,
+        },
+        "imul" => destination.plus(source),
+        // a remainder is narrower than both sides; only a constant divisor
+        // is worth using
+        "imod" => match source
+        {
+            Bound::Finite { log: 0, linear: 0, .. } => source,
+            _other => destination,
+        },
+        "bsl" => match Operand::parse(instruction.operand(1))
+        {
+            Some(Operand::Immediate(shift)) if shift >= 0 => destination.plus(Bound::constant(shift as usize)),
+            _other => Bound::Unbounded,
+        },
+        _other => Bound::constant(1),
+    }
+}
+
+fn clamped_width(instruction: &Instruction, state: &State, limit: Option<&Bound>) -> Bound
+{
+    let width: Bound = written_width(instruction, state);
+
+    return match limit
+    {
+        Some(limit) if !width.is_within(limit) => *limit,
+        _other => width,
+    };
+}
+
+pub fn estimate_space(program: &[Instruction]) -> SpaceEstimate
+{
+    let mut states: Vec<Option<State>> = vec![None; program.len()];
+    let mut widened: BTreeMap<usize, Bound> = BTreeMap::new();
+    let mut variables: BTreeMap<usize, Bound> = BTreeMap::new();
+    let mut num_bits: usize = 0;
+    // how many registers an indexed write creates depends on the data
+    let mut writes_indexed: bool = false;
+
+    let mut pending: Vec<usize> = Vec::new();
+    if !program.is_empty()
+    {
+        states[0] = Some(State::new());
+        pending.push(0);
+    }
+
+    while let Some(line) = pending.pop()
+    {
+        let mut state: State = match &states[line]
+        {
+            Some(state) => state.clone(),
+            None => continue,
+        };
+        let instruction: &Instruction = &program[line];
+
+        for destination in instruction.written_operands()
+        {
+            if let Operand::Variable(index) = destination
+            {
+                state.insert(index, clamped_width(instruction, &state, widened.get(&index)));
+            }
+        }
+
+        for successor in instruction.successors(line)
+        {
+            if successor >= program.len()
+            {
+                continue;
+            }
+
+            let mut merged: State = states[successor].clone().unwrap_or_default();
+            let mut changed: bool = states[successor].is_none();
+
+            for (index, width) in state.iter()
+            {
+                let old: Option<Bound> = merged.get(index).copied();
+                let mut new: Bound = old.map_or(*width, |old| old.max(*width));
+
+                // a register that grows around a loop is bounded by whatever
+                // the loop's exit test compares it against, or not at all
+                if old.is_some() && old != Some(new) && successor <= line
+                {
+                    let limit: Bound = widened.get(index).copied()
+                        .unwrap_or_else(|| guard_bound(program, &merged, *index, successor, line));
+                    new = new.max(limit);
+                    widened.insert(*index, new);
+                }
+
+                if old != Some(new)
+                {
+                    merged.insert(*index, new);
+                    changed = true;
+                }
+            }
+
+            if changed
+            {
+                states[successor] = Some(merged);
+                pending.push(successor);
+            }
+        }
+    }
+
+    for (line, instruction) in program.iter().enumerate()
+    {
+        let state: &State = match &states[line]
+        {
+            Some(state) => state,
+            None => continue,
+        };
+
+        for destination in instruction.written_operands()
+        {
+            match destination
+            {
+                Operand::Variable(index) =>
+                {
+                    let width: Bound = clamped_width(instruction, state, widened.get(&index));
+                    variables.insert(index, variables.get(&index).map_or(width, |bound| bound.max(width)));
+                }
+                Operand::Bit(index) =>
+                {
+                    num_bits = num_bits.max(index + 1);
+                }
+                Operand::VariableAt(_) | Operand::BitAt(_) =>
+                {
+                    writes_indexed = true;
+                }
+                _other => {}
+            }
+        }
+    }
+
+    let total: Bound = match writes_indexed
+    {
+        true => Bound::Unbounded,
+        false => variables.values()
+            .fold(Bound::constant(num_bits + NUM_FLAGS), |total, width| total.plus(*width)),
+    };
+
+    return SpaceEstimate { variables, num_bits, total };
+}
+
+// the `jon` that decides whether the loop from `start` back from `end` runs
+// again: one right before the back edge, or else the first in the straight
+// run of lines at the loop's head, so it is tested on every pass
+fn exit_test(program: &[Instruction], start: usize, end: usize) -> Option<usize>
+{
+    let leaves = |line: usize| -> bool
+    {
+        match program.get(line)
+        {
+            Some(instruction) if line >= start && line <= end =>
+            {
+                instruction.successors(line).iter().all(|successor| *successor < start || *successor > end)
+            }
+            _other => true,
+        }
+    };
+
+    let test: usize = match end.checked_sub(1)
+    {
+        Some(before) if before >= start && program[before].operator == "jon" => before,
+        _other => (start..end).find(|line| matches!(program[*line].operator.as_str(), "jmp" | "jon" | "end" | "acc" | "rej"))
+            .filter(|line| program[*line].operator == "jon")?,
+    };
+
+    return match leaves(test + 1) || leaves(test + 2)
+    {
+        true => Some(test),
+        false => None,
+    };
+}
+
+// widest value a register is compared against by the test that ends its
+// loop, plus one for a counter that steps past it
+fn guard_bound(program: &[Instruction], state: &State, index: usize, start: usize, end: usize) -> Bound
+{
+    let instruction: &Instruction = match exit_test(program, start, end)
+        .and_then(|test| test.checked_sub(1))
+        .filter(|compare| *compare >= start)
+    {
+        Some(compare) if program[compare].operator == "cmp" => &program[compare],
+        _other => return Bound::Unbounded,
+    };
+
+    let other: &str = match (Operand::parse(instruction.operand(0)), Operand::parse(instruction.operand(1)))
+    {
+        (Some(Operand::Variable(a)), Some(Operand::Variable(b))) if a == index && b == index => return Bound::Unbounded,
+        (Some(Operand::Variable(a)), _) if a == index => instruction.operand(1),
+        (_, Some(Operand::Variable(b))) if b == index => instruction.operand(0),
+        _other => return Bound::Unbounded,
+    };
+
+    // a guard that is itself still growing cannot bound anything yet
+    return match Operand::parse(other)
+    {
+        Some(Operand::Variable(other_index)) if !state.contains_key(&other_index) => Bound::Unbounded,
+        _other => operand_width(other, state).plus(Bound::constant(1)),
+    };
+}
@@ -0,0 +1,432 @@
+use crate::instruction::{
+    Instruction,
+    Operand,
+    CONDITIONS,
+    OPCODES,
+};
+
+pub const BYTECODE_MAGIC: [u8; 4] = *b"MUNB";
+pub const BYTECODE_VERSION: u16 = 1;
+// bumped whenever an opcode or operand kind is added or an operand's encoding
+// changes; opcodes are numbered by their position in OPCODES, so new ones go
+// at the end
+pub const ISA_VERSION: u16 = 10;
+// from this ISA on an immediate is an 8 byte signed value; before, it was a
+// 4 byte unsigned one
+const SIGNED_IMMEDIATE_ISA: u16 = 10;
+
+const OPERAND_VARIABLE: u8 = 0;
+const OPERAND_BIT: u8 = 1;
+const OPERAND_INPUT: u8 = 2;
+const OPERAND_IMMEDIATE: u8 = 3;
+const OPERAND_CONDITION: u8 = 4;
+const OPERAND_VARIABLE_AT: u8 = 5;
+const OPERAND_BIT_AT: u8 = 6;
+const OPERAND_INPUT_AT: u8 = 7;
+
+const SECTION_LABELS: u8 = 1;
+const SECTION_DEBUG: u8 = 2;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bytecode
+{
+    pub program:    Vec<Instruction>,
+    // label names and the lines they point to
+    pub labels:     Vec<(String, usize)>,
+    // where each instruction came from, one entry per instruction
+    pub debug:      Vec<String>,
+}
+
+fn crc32(bytes: &[u8]) -> u32
+{
+    let mut crc: u32 = 0xffffffff;
+
+    for byte in bytes
+    {
+        crc ^= *byte as u32;
+        for _ in 0..8
+        {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+
+    return !crc;
+}
+
+fn push_string(bytes: &mut Vec<u8>, string: &str)
+{
+    bytes.extend((string.len() as u16).to_le_bytes());
+    bytes.extend(string.as_bytes());
+}
+
+struct Reader<'a>
+{
+    bytes:      &'a [u8],
+    position:   usize,
+}
+
+impl<'a> Reader<'a>
+{
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String>
+    {
+        if self.position + length > self.bytes.len()
+        {
+            return Err(format!("bytecode ends early at byte {}", self.position));
+        }
+
+        let taken: &[u8] = &self.bytes[self.position..self.position + length];
+        self.position += length;
+
+        return Ok(taken);
+    }
+
+    fn u8(&mut self) -> Result<u8, String>
+    {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn u16(&mut self) -> Result<u16, String>
+    {
+        return Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()));
+    }
+
+    fn u32(&mut self) -> Result<u32, String>
+    {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn i64(&mut self) -> Result<i64, String>
+    {
+        return Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+    }
+
+    fn string(&mut self) -> Result<String, String>
+    {
+        let length: usize = self.u16()? as usize;
+
+        return String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| "bytecode string is not UTF-8".to_string());
+    }
+}
+
+impl Bytecode
+{
+    pub fn from_program(program: &[Instruction]) -> Self
+    {
+        Self
+        {
+            program:    program.to_vec(),
+            labels:     Vec::new(),
+            debug:      Vec::new(),
+        }
+    }
+
+    pub fn is_bytecode(bytes: &[u8]) -> bool
+    {
+        return bytes.starts_with(&BYTECODE_MAGIC);
+    }
+
+    fn encode_operand(instruction: &Instruction, index: usize, operand: &str) -> (u8, i64)
+    {
+        if instruction.condition_operand() == Some(index)
+        {
+            let condition: usize = CONDITIONS.iter().position(|condition| *condition == operand)
+                .unwrap_or_else(|| panic!("Invalid condition: {}", operand));
+            return (OPERAND_CONDITION, condition as i64);
+        }
+
+        match Operand::parse(operand)
+        {
+            Some(Operand::Variable(index)) => (OPERAND_VARIABLE, index as i64),
+            Some(Operand::Bit(index)) => (OPERAND_BIT, index as i64),
+            Some(Operand::Input(index)) => (OPERAND_INPUT, index as i64),
+            Some(Operand::Immediate(value)) => (OPERAND_IMMEDIATE, value),
+            Some(Operand::VariableAt(index)) => (OPERAND_VARIABLE_AT, index as i64),
+            Some(Operand::BitAt(index)) => (OPERAND_BIT_AT, index as i64),
+            Some(Operand::InputAt(index)) => (OPERAND_INPUT_AT, index as i64),
+            None => panic!("Invalid operand {} in: {}", operand, instruction),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8>
+    {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        bytes.extend(BYTECODE_MAGIC);
+        bytes.extend(BYTECODE_VERSION.to_le_bytes());
+        bytes.extend(ISA_VERSION.to_le_bytes());
+        bytes.extend((self.program.len() as u32).to_le_bytes());
+
+        for instruction in self.program.iter()
+        {
+            let opcode: usize = OPCODES.iter().position(|info| info.operator == instruction.operator)
+                .unwrap_or_else(|| panic!("Unknown operator: {}", instruction.operator));
+
+            // `jon` with no condition still stores the empty condition
+            let operands: Vec<&str> = if instruction.operator == "jon" && instruction.operands.is_empty()
+            {
+                vec![""]
+            } else
+            {
+                instruction.operands.iter().map(|operand| operand.as_str()).collect::<Vec<&str>>()
+            };
+
+            bytes.push(opcode as u8);
+            bytes.push(operands.len() as u8);
+            for (index, operand) in operands.into_iter().enumerate()
+            {
+                let (kind, value) = Self::encode_operand(instruction, index, operand);
+                bytes.push(kind);
+                match kind
+                {
+                    OPERAND_IMMEDIATE => bytes.extend(value.to_le_bytes()),
+                    _other => bytes.extend((value as u32).to_le_bytes()),
+                }
+            }
+        }
+
+        let mut sections: Vec<(u8, Vec<u8>)> = Vec::new();
+
+        if !self.labels.is_empty()
+        {
+            let mut section: Vec<u8> = Vec::new();
+            section.extend((self.labels.len() as u32).to_le_bytes());
+            for (name, line) in self.labels.iter()
+            {
+                section.extend((*line as u32).to_le_bytes());
+                push_string(&mut section, name);
+            }
+            sections.push((SECTION_LABELS, section));
+        }
+
+        if !self.debug.is_empty()
+        {
+            let mut section: Vec<u8> = Vec::new();
+            section.extend((self.debug.len() as u32).to_le_bytes());
+            for source in self.debug.iter()
+            {
+                push_string(&mut section, source);
+            }
+            sections.push((SECTION_DEBUG, section));
+        }
+
+        bytes.extend((sections.len() as u16).to_le_bytes());
+        for (tag, section) in sections
+        {
+            bytes.push(tag);
+            bytes.extend((section.len() as u32).to_le_bytes());
+            bytes.extend(section);
+        }
+
+        let checksum: u32 = crc32(&bytes);
+        bytes.extend(checksum.to_le_bytes());
+
+        return bytes;
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String>
+    {
+        if !Self::is_bytecode(bytes)
+        {
+            return Err("not a Munin bytecode file".to_string());
+        }
+        if bytes.len() < BYTECODE_MAGIC.len() + 4
+        {
+            return Err("bytecode ends early".to_string());
+        }
+
+        let (body, checksum) = bytes.split_at(bytes.len() - 4);
+        if crc32(body) != u32::from_le_bytes(checksum.try_into().unwrap())
+        {
+            return Err("bytecode checksum does not match".to_string());
+        }
+
+        let mut reader: Reader = Reader { bytes: body, position: BYTECODE_MAGIC.len() };
+
+        let version: u16 = reader.u16()?;
+        if version != BYTECODE_VERSION
+        {
+            return Err(format!("unsupported bytecode version {}", version));
+        }
+        let isa_version: u16 = reader.u16()?;
+        if isa_version > ISA_VERSION
+        {
+            return Err(format!("bytecode needs ISA version {} but this device has {}", isa_version, ISA_VERSION));
+        }
+
+        let num_instructions: usize = reader.u32()? as usize;
+        let mut bytecode: Bytecode = Bytecode::default();
+
+        for _ in 0..num_instructions
+        {
+            let opcode: usize = reader.u8()? as usize;
+            let info = OPCODES.get(opcode).ok_or(format!("unknown opcode {}", opcode))?;
+            let num_operands: usize = reader.u8()? as usize;
+            let mut operands: Vec<String> = Vec::new();
+
+            for _ in 0..num_operands
+            {
+                let kind: u8 = reader.u8()?;
+                let value: i64 = match kind
+                {
+                    OPERAND_IMMEDIATE if isa_version >= SIGNED_IMMEDIATE_ISA => reader.i64()?,
+                    _other => reader.u32()? as i64,
+                };
+                let operand: String = match kind
+                {
+                    OPERAND_VARIABLE => Operand::Variable(value as usize).to_string(),
+                    OPERAND_BIT => Operand::Bit(value as usize).to_string(),
+                    OPERAND_INPUT => Operand::Input(value as usize).to_string(),
+                    OPERAND_IMMEDIATE => Operand::Immediate(value).to_string(),
+                    OPERAND_VARIABLE_AT => Operand::VariableAt(value as usize).to_string(),
+                    OPERAND_BIT_AT => Operand::BitAt(value as usize).to_string(),
+                    OPERAND_INPUT_AT => Operand::InputAt(value as usize).to_string(),
+                    OPERAND_CONDITION => CONDITIONS.get(value as usize)
+                        .ok_or(format!("unknown condition {}", value))?
+                        .to_string(),
+                    _other => return Err(format!("unknown operand kind {}", kind)),
+                };
+                if !operand.is_empty()
+                {
+                    operands.push(operand);
+                }
+            }
+
+            bytecode.program.push(Instruction { operator: info.operator.to_string(), operands });
+        }
+
+        let num_sections: usize = reader.u16()? as usize;
+        for _ in 0..num_sections
+        {
+            let tag: u8 = reader.u8()?;
+            let length: usize = reader.u32()? as usize;
+            let mut section: Reader = Reader { bytes: reader.take(length)?, position: 0 };
+
+            match tag
+            {
+                SECTION_LABELS =>
+                {
+                    for _ in 0..section.u32()?
+                    {
+                        let line: usize = section.u32()? as usize;
+                        bytecode.labels.push((section.string()?, line));
+                    }
+                }
+                SECTION_DEBUG =>
+                {
+                    for _ in 0..section.u32()?
+                    {
+                        bytecode.debug.push(section.string()?);
+                    }
+                }
+                // sections from newer versions are skipped
+                _other => {}
+            }
+        }
+
+        return Ok(bytecode);
+    }
+
+    // the program as Munin assembly; labels and sources become comments, one
+    // for each run of instructions from the same source line
+    pub fn to_text(&self) -> Vec<String>
+    {
+        let mut lines: Vec<String> = Vec::new();
+        let mut previous_source: Option<&String> = None;
+
+        for (line, instruction) in self.program.iter().enumerate()
+        {
+            for (name, _) in self.labels.iter().filter(|(_, label_line)| *label_line == line)
+            {
+                lines.push(format!("; label {}", name));
+            }
+
+            let source: Option<&String> = self.debug.get(line);
+            if let Some(text) = source.filter(|text| Some(*text) != previous_source)
+            {
+                lines.push(format!("; {}", text));
+            }
+            previous_source = source;
+
+            lines.push(instruction.to_string());
+        }
+
+        return lines;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // operands are written as the decoder prints them
+    fn program(lines: &[&str]) -> Vec<Instruction>
+    {
+        return lines.iter().map(|line| Instruction::parse(line)).collect::<Vec<Instruction>>();
+    }
+
+    // a body with its checksum recomputed
+    fn seal(mut body: Vec<u8>) -> Vec<u8>
+    {
+        let checksum: u32 = crc32(&body);
+        body.extend(checksum.to_le_bytes());
+        return body;
+    }
+
+    #[test]
+    fn round_trip()
+    {
+        let mut bytecode: Bytecode = Bytecode::from_program(&program(&["set v00 -3", "cmp v00 i00", "jon", "jon ge", "jmp 0", "set v[v00] b01", "acc"]));
+        bytecode.labels = vec![("main::top".to_string(), 0)];
+        bytecode.debug = (0..bytecode.program.len()).map(|line| format!("top.mns1:{}", line + 1)).collect::<Vec<String>>();
+
+        assert_eq!(Bytecode::decode(&bytecode.encode()), Ok(bytecode));
+    }
+
+    #[test]
+    fn legacy_immediates_are_unsigned_u32()
+    {
+        let set: usize = OPCODES.iter().position(|info| info.operator == "set").unwrap();
+
+        let mut body: Vec<u8> = Vec::new();
+        body.extend(BYTECODE_MAGIC);
+        body.extend(BYTECODE_VERSION.to_le_bytes());
+        body.extend((SIGNED_IMMEDIATE_ISA - 1).to_le_bytes());
+        body.extend(1u32.to_le_bytes());
+        body.extend([set as u8, 2, OPERAND_VARIABLE]);
+        body.extend(0u32.to_le_bytes());
+        body.push(OPERAND_IMMEDIATE);
+        body.extend(u32::MAX.to_le_bytes());
+        body.extend(0u16.to_le_bytes());
+
+        let bytecode: Bytecode = Bytecode::decode(&seal(body)).unwrap();
+        assert_eq!(bytecode.program, program(&["set v00 4294967295"]));
+    }
+
+    #[test]
+    fn rejects_a_bad_checksum()
+    {
+        let mut bytes: Vec<u8> = Bytecode::from_program(&program(&["set v00 1", "end"])).encode();
+        let last: usize = bytes.len() - 5;
+        bytes[last] ^= 1;
+
+        assert_eq!(Bytecode::decode(&bytes), Err("bytecode checksum does not match".to_string()));
+    }
+
+    #[test]
+    fn skips_unknown_sections()
+    {
+        let bytecode: Bytecode = Bytecode::from_program(&program(&["set v00 1", "end"]));
+        let bytes: Vec<u8> = bytecode.encode();
+
+        // replace the empty section count with one section of an unknown tag
+        let mut body: Vec<u8> = bytes[..bytes.len() - 6].to_vec();
+        body.extend(1u16.to_le_bytes());
+        body.push(99);
+        body.extend(3u32.to_le_bytes());
+        body.extend([1, 2, 3]);
+
+        assert_eq!(Bytecode::decode(&seal(body)), Ok(bytecode));
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use crate::instruction::Instruction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind
+{
+    FallThrough,
+    Jump,
+    // taken when a `jon` condition holds
+    Skip,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock
+{
+    pub start:  usize,
+    pub end:    usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge
+{
+    pub from:   usize,
+    pub to:     usize,
+    pub kind:   EdgeKind,
+}
+
+// per-line and per-transition execution counts from a run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile
+{
+    pub line_executions:    Vec<u32>,
+    pub edge_executions:    BTreeMap<(usize, usize), u32>,
+}
+
+pub struct ControlFlowGraph
+{
+    pub program:    Vec<Instruction>,
+    pub blocks:     Vec<BasicBlock>,
+    pub edges:      Vec<Edge>,
+}
+
+impl ControlFlowGraph
+{
+    pub fn build(program: &[Instruction]) -> Self
+    {
+        let mut leaders: BTreeSet<usize> = BTreeSet::new();
+
+        if !program.is_empty()
+        {
+            leaders.insert(0);
+        }
+
+        for (line, instruction) in program.iter().enumerate()
+        {
+            match instruction.operator.as_str()
+            {
+                "jmp" | "jon" | "end" | "acc" | "rej" =>
+                {
+                    leaders.extend(instruction.successors(line));
+                    leaders.insert(line + 1);
+                }
+                _other => {}
+            }
+        }
+
+        let leaders: Vec<usize> = leaders.into_iter()
+            .filter(|leader| *leader < program.len())
+            .collect::<Vec<usize>>();
+
+        let blocks: Vec<BasicBlock> = leaders.iter()
+            .enumerate()
+            .map(|(index, start)| BasicBlock
+            {
+                start:  *start,
+                end:    leaders.get(index + 1).map_or(program.len(), |next| *next) - 1,
+            })
+            .collect::<Vec<BasicBlock>>();
+
+        let mut edges: Vec<Edge> = Vec::new();
+
+        for (index, block) in blocks.iter().enumerate()
+        {
+            let last: &Instruction = &program[block.end];
+
+            for (position, successor) in last.successors(block.end).into_iter().enumerate()
+            {
+                let kind: EdgeKind = match (last.operator.as_str(), position)
+                {
+                    ("jmp", _) => EdgeKind::Jump,
+                    ("jon", 1) => EdgeKind::Skip,
+                    _other => EdgeKind::FallThrough,
+                };
+
+                if let Some(to) = blocks.iter().position(|block| block.start == successor)
+                {
+                    edges.push(Edge { from: index, to, kind });
+                }
+            }
+        }
+
+        return Self { program: program.to_vec(), blocks, edges };
+    }
+
+    pub fn block_executions(&self, profile: &Profile, block: usize) -> u32
+    {
+        return profile.line_executions.get(self.blocks[block].start).copied().unwrap_or(0);
+    }
+
+    pub fn edge_executions(&self, profile: &Profile, edge: &Edge) -> u32
+    {
+        let from_line: usize = self.blocks[edge.from].end;
+        let to_line: usize = self.blocks[edge.to].start;
+
+        return profile.edge_executions.get(&(from_line, to_line)).copied().unwrap_or(0);
+    }
+
+    // cold is white for blocks and grey for edges, the hottest is red
+    fn heat_colour(executions: u32, max_executions: u32, cold_brightness: f64) -> String
+    {
+        let heat: f64 = if max_executions == 0 { 0.0 } else { executions as f64 / max_executions as f64 };
+
+        return format!("0.000 {:.3} {:.3}", heat, cold_brightness + (1.0 - cold_brightness) * heat);
+    }
+
+    pub fn to_dot(&self, profile: Option<&Profile>) -> String
+    {
+        let mut dot: String = String::new();
+
+        let max_block_executions: u32 = profile.map_or(0, |profile| (0..self.blocks.len())
+            .map(|block| self.block_executions(profile, block))
+            .max()
+            .unwrap_or(0));
+        let max_edge_executions: u32 = profile.map_or(0, |profile| self.edges.iter()
+            .map(|edge| self.edge_executions(profile, edge))
+            .max()
+            .unwrap_or(0));
+
+        writeln!(dot, "digraph munin {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        for (index, block) in self.blocks.iter().enumerate()
+        {
+            let mut label: String = String::new();
+            for line in block.start..=block.end
+            {
+                write!(label, "{}: {}\\l", line, self.program[line]).unwrap();
+            }
+
+            match profile
+            {
+                Some(profile) =>
+                {
+                    let executions: u32 = self.block_executions(profile, index);
+                    writeln!(
+                        dot,
+                        "    b{} [label=\"{}executed {}\\l\", style=filled, fillcolor=\"{}\"];",
+                        index, label, executions, Self::heat_colour(executions, max_block_executions, 1.0)
+                    ).unwrap();
+                }
+                None =>
+                {
+                    writeln!(dot, "    b{} [label=\"{}\"];", index, label).unwrap();
+                }
+            }
+        }
+
+        for edge in self.edges.iter()
+        {
+            let kind: &str = match edge.kind
+            {
+                EdgeKind::FallThrough => "",
+                EdgeKind::Jump => "jump",
+                EdgeKind::Skip => "skip",
+            };
+
+            match profile
+            {
+                Some(profile) =>
+                {
+                    let executions: u32 = self.edge_executions(profile, edge);
+                    let width: f64 = if max_edge_executions == 0 { 1.0 } else { 1.0 + 4.0 * executions as f64 / max_edge_executions as f64 };
+                    writeln!(
+                        dot,
+                        "    b{} -> b{} [label=\"{}\", penwidth={:.2}, color=\"{}\"];",
+                        edge.from, edge.to, format!("{} {}", kind, executions).trim(), width,
+                        Self::heat_colour(executions, max_edge_executions, 0.5)
+                    ).unwrap();
+                }
+                None =>
+                {
+                    writeln!(dot, "    b{} -> b{} [label=\"{}\"];", edge.from, edge.to, kind).unwrap();
+                }
+            }
+        }
+
+        writeln!(dot, "}}").unwrap();
+
+        return dot;
+    }
+}
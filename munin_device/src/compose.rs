@@ -0,0 +1,169 @@
+use std::fmt;
+
+use crate::{
+    access::{
+        Head,
+        InputAccess,
+    },
+    input::InputValue,
+    outcome::Outcome,
+    report::SpaceUsage,
+    Device,
+};
+
+// the output of a first program, recomputed one bit at a time instead of
+// being stored; it stands in for i00 of the second program
+pub struct RecomputedInput
+{
+    // the first program, with its inputs loaded
+    pub device:     Device,
+    pub length:     usize,
+    pub runs:       u32,
+    pub steps:      u64,
+    // the most execution space any one run used
+    pub max_space:  usize,
+}
+
+impl RecomputedInput
+{
+    // one run only counts the output bits, so that the second program's head
+    // knows where the end marker is
+    pub fn new(device: Device) -> Self
+    {
+        let mut recomputed: RecomputedInput = Self
+        {
+            device,
+            length:     0,
+            runs:       0,
+            steps:      0,
+            max_space:  0,
+        };
+
+        recomputed.run(usize::MAX);
+        recomputed.length = recomputed.device.output_written;
+
+        return recomputed;
+    }
+
+    fn run(&mut self, position: usize) -> Option<bool>
+    {
+        self.device.capture_output = Some(position);
+        self.device.execute_program(None);
+
+        self.max_space = self.max_space.max(self.device.space_usage().execution());
+        self.runs += 1;
+        self.steps += self.device.instructions_executed as u64;
+
+        return self.device.captured_bit;
+    }
+
+    pub fn bit(&mut self, position: usize) -> bool
+    {
+        return self.run(position).unwrap_or_else(|| panic!("the first program wrote no bit {} on a rerun", position));
+    }
+}
+
+pub struct CompositionReport
+{
+    pub outcome:            Outcome,
+    // the intermediate string's length, never stored
+    pub intermediate_length: usize,
+    pub first_space:        usize,
+    pub second_space:       usize,
+    pub index_bits:         usize,
+    pub first_runs:         u32,
+    pub composed_steps:     u64,
+    // running the first program once, storing its output, then the second
+    pub direct_outcome:     Outcome,
+    pub direct_steps:       u64,
+}
+
+impl CompositionReport
+{
+    pub fn work_space(&self) -> usize
+    {
+        return self.first_space + self.second_space + self.index_bits;
+    }
+
+    pub fn time_blowup(&self) -> f64
+    {
+        return self.composed_steps as f64 / self.direct_steps.max(1) as f64;
+    }
+}
+
+impl fmt::Display for CompositionReport
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        writeln!(f, " COMPOSITION")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}|{}", "MEMORY", "BITS USED")?;
+        writeln!(f, "{:->12}{:->24}", "+", "")?;
+        writeln!(f, "{: ^11}| {}", "FIRST", self.first_space)?;
+        writeln!(f, "{: ^11}| {}", "SECOND", self.second_space)?;
+        writeln!(f, "{: ^11}| {}", "INDEX", self.index_bits)?;
+        writeln!(f, "{: ^11}| {}", "TOTAL", self.work_space())?;
+        writeln!(f, "{: ^11}| {}", "NOT STORED", self.intermediate_length)?;
+        writeln!(f, " RESULT")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}| {}", "OUTCOME", self.outcome.name())?;
+        writeln!(f, "{: ^11}| {}", "DIRECT", self.direct_outcome.name())?;
+        writeln!(f, " TIME USAGE")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}| {}", "RERUNS", self.first_runs)?;
+        writeln!(f, "{: ^11}| {}", "COMPOSED", self.composed_steps)?;
+        writeln!(f, "{: ^11}| {}", "DIRECT", self.direct_steps)?;
+        write!(f, "{: ^11}| {:.2}x", "BLOW-UP", self.time_blowup())
+    }
+}
+
+// runs `second` on the output of `first` without storing it: every time
+// `second` reads bit k of i00, `first` runs again and only its k-th output
+// bit is kept; `second` must read i00 through a head
+pub fn compose(first: Device, second: Device) -> CompositionReport
+{
+    if second.input_access == InputAccess::RandomAccess
+    {
+        panic!("the second program must read its input through a head; use two-way or streaming input access");
+    }
+
+    let program_lines: Vec<String> = second.program_lines.clone();
+    let (arithmetic, input_access) = (second.arithmetic, second.input_access);
+
+    // the direct run stores the intermediate string as an ordinary input
+    let mut first: Device = first;
+    first.capture_output = None;
+    first.execute_program(None);
+    let intermediate: InputValue = InputValue::Bits(first.output.clone());
+    let first_steps: u64 = first.instructions_executed as u64;
+
+    let mut direct: Device = Device::new();
+    direct.arithmetic = arithmetic;
+    direct.input_access = input_access;
+    direct.program_lines = program_lines;
+    direct.load_input("i00", &intermediate);
+    let direct_outcome: Outcome = direct.execute_program(None);
+    let direct_steps: u64 = first_steps + direct.instructions_executed as u64;
+
+    let mut second: Device = second;
+    second.recomputed = Some(Box::new(RecomputedInput::new(first)));
+    second.has_loaded_input = true;
+    let outcome: Outcome = second.execute_program(None);
+
+    let space: SpaceUsage = second.space_usage();
+    let second_space: usize = space.flags + space.registers;
+    let recomputed: Box<RecomputedInput> = second.recomputed.take().unwrap();
+
+    return CompositionReport
+    {
+        outcome,
+        intermediate_length:    recomputed.length,
+        first_space:            recomputed.max_space,
+        second_space,
+        index_bits:             Head::position_bits(recomputed.length),
+        first_runs:             recomputed.runs,
+        composed_steps:         recomputed.steps + second.instructions_executed as u64,
+        direct_outcome,
+        direct_steps,
+    };
+}
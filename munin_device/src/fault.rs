@@ -0,0 +1,76 @@
+use std::fmt;
+
+// an error in the program being run, as opposed to a bug in the device; the
+// program stops at the faulting line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault
+{
+    DivisionByZero
+    {
+        line:   usize,
+    },
+    // a value below the smallest the arithmetic mode allows
+    Underflow
+    {
+        line:   usize,
+    },
+    // a value above the largest the arithmetic mode allows
+    Overflow
+    {
+        line:   usize,
+    },
+    // reading an input in a way the input access mode does not allow
+    InputAccess
+    {
+        line:   usize,
+        reason: String,
+    },
+    // moving a head past an end marker
+    HeadOffTape
+    {
+        line:   usize,
+        input:  usize,
+    },
+    // an indexed operand naming a register that does not exist
+    IndexOutOfRange
+    {
+        line:       usize,
+        operand:    String,
+        register:   String,
+    },
+}
+
+impl Fault
+{
+    pub fn line(&self) -> usize
+    {
+        match self
+        {
+            Fault::DivisionByZero { line }
+            | Fault::Underflow { line }
+            | Fault::Overflow { line }
+            | Fault::InputAccess { line, .. }
+            | Fault::HeadOffTape { line, .. }
+            | Fault::IndexOutOfRange { line, .. } => *line,
+        }
+    }
+}
+
+impl fmt::Display for Fault
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Fault::DivisionByZero { line } => write!(f, "line {}: division by zero", line),
+            Fault::Underflow { line } => write!(f, "line {}: value below the range of the arithmetic mode", line),
+            Fault::Overflow { line } => write!(f, "line {}: value above the range of the arithmetic mode", line),
+            Fault::InputAccess { line, reason } => write!(f, "line {}: {}", line, reason),
+            Fault::HeadOffTape { line, input } => write!(f, "line {}: the head of i{:02} moves off the tape", line, input),
+            Fault::IndexOutOfRange { line, operand, register } =>
+            {
+                write!(f, "line {}: {} points at {}, which does not exist", line, operand, register)
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    access::Head,
+    fault::Fault,
+    instruction::{
+        Instruction,
+        Operand,
+    },
+    random::Rng,
+    Device,
+    Variable,
+    NUM_FLAGS,
+};
+
+// everything one instruction can change, as it was before the instruction ran
+#[derive(Debug, Clone)]
+pub struct UndoRecord
+{
+    pub instruction_pointer:    usize,
+    pub flags:                  [bool; NUM_FLAGS],
+    // the register the instruction writes and its old value, with None for
+    // a register the instruction creates
+    pub register:               Option<(Operand, Option<Variable>)>,
+    pub heads:                  Vec<Head>,
+    pub output_length:          usize,
+    pub output_written:         usize,
+    pub verdict:                Option<bool>,
+    pub fault:                  Option<Fault>,
+    pub program_running:        bool,
+    pub guesses_used:           usize,
+    pub rng:                    Rng,
+}
+
+impl UndoRecord
+{
+    pub fn writes(&self, register: &Operand) -> bool
+    {
+        return self.register.as_ref().is_some_and(|(written, _)| written == register);
+    }
+}
+
+impl Device
+{
+    pub(crate) fn record_undo(&mut self, instruction: &str)
+    {
+        let instruction: Instruction = Instruction::parse(instruction);
+        let destination: Option<String> = instruction.info()
+            .and_then(|info| info.writes.first())
+            .map(|index| instruction.operand(*index).to_string());
+
+        // an indexed destination is resolved the way the instruction will;
+        // if that faults, nothing is written
+        let register: Option<Operand> = destination
+            .and_then(|destination| self.resolve_operand(&destination, true).ok())
+            .and_then(|register| Operand::parse(&register));
+
+        let register: Option<(Operand, Option<Variable>)> = register.and_then(|register| match register
+        {
+            Operand::Variable(index) => Some((register, self.write_variables.get(index).cloned())),
+            Operand::Bit(index) => Some((register, self.write_bits.get(index).cloned())),
+            _other => None,
+        });
+
+        self.history.push_back(UndoRecord
+        {
+            instruction_pointer:    self.instruction_pointer,
+            flags:                  self.flags,
+            register,
+            heads:                  self.heads.clone(),
+            output_length:          self.output.len(),
+            output_written:         self.output_written,
+            verdict:                self.verdict,
+            fault:                  self.fault.clone(),
+            program_running:        self.program_running,
+            guesses_used:           self.guesses_used,
+            rng:                    self.rng.clone(),
+        });
+
+        if self.history.len() > self.history_limit
+        {
+            self.history.pop_front();
+        }
+    }
+
+    fn undo(&mut self, record: UndoRecord)
+    {
+        match record.register
+        {
+            Some((Operand::Variable(index), Some(old))) => self.write_variables[index] = old,
+            Some((Operand::Variable(index), None)) => self.write_variables.truncate(index),
+            Some((Operand::Bit(index), Some(old))) => self.write_bits[index] = old,
+            Some((Operand::Bit(index), None)) => self.write_bits.truncate(index),
+            _other => {}
+        }
+
+        self.instruction_pointer = record.instruction_pointer;
+        self.flags = record.flags;
+        self.heads = record.heads;
+        self.output.truncate(record.output_length);
+        self.output_written = record.output_written;
+        self.verdict = record.verdict;
+        self.fault = record.fault;
+        self.program_running = record.program_running;
+        self.guesses_used = record.guesses_used;
+        self.rng = record.rng;
+        self.instructions_executed -= 1;
+    }
+
+    // runs up to `steps` more instructions, recording them if history is on
+    pub fn step(&mut self, steps: u32)
+    {
+        self.pause_at = Some(self.instructions_executed + steps);
+        self.resume();
+        self.pause_at = None;
+    }
+
+    // undoes up to `steps` instructions; returns how many the history held
+    pub fn back(&mut self, steps: usize) -> usize
+    {
+        let mut undone: usize = 0;
+
+        while undone < steps
+        {
+            match self.history.pop_back()
+            {
+                Some(record) => self.undo(record),
+                None => break,
+            }
+            undone += 1;
+        }
+
+        return undone;
+    }
+
+    // undoes instructions up to and including the last one that wrote
+    // `register`, leaving the device about to run it again; false if the
+    // history does not reach that far, with everything in it undone
+    pub fn reverse_to_write(&mut self, register: &Operand) -> bool
+    {
+        while let Some(record) = self.history.pop_back()
+        {
+            let found: bool = record.writes(register);
+            self.undo(record);
+
+            if found
+            {
+                return true;
+            }
+        }
+
+        return false;
+    }
+}
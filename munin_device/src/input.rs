@@ -0,0 +1,213 @@
+use std::{
+    fmt,
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use bit_vec::BitVec;
+
+// which end of a written bitstring is bit 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitOrder
+{
+    // the last character is bit 0, as in a binary number
+    #[default]
+    MsbFirst,
+    // the first character is bit 0
+    LsbFirst,
+}
+
+impl FromStr for BitOrder
+{
+    type Err = String;
+
+    fn from_str(order: &str) -> Result<Self, Self::Err>
+    {
+        match order
+        {
+            "msb" => Ok(BitOrder::MsbFirst),
+            "lsb" => Ok(BitOrder::LsbFirst),
+            _other => Err(format!("unknown bit order `{}`; expected msb or lsb", order)),
+        }
+    }
+}
+
+impl fmt::Display for BitOrder
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            BitOrder::MsbFirst => write!(f, "msb"),
+            BitOrder::LsbFirst => write!(f, "lsb"),
+        }
+    }
+}
+
+// how an input file is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat
+{
+    // the bytes of the file as one bitstring, each byte high bit first
+    Raw,
+    // `0` and `1` characters as one bitstring; whitespace is ignored
+    Bits,
+    // hex digits as one bitstring of 4 bits per digit; whitespace is ignored
+    Hex,
+    // one input per line, written as on the command line; blank lines and
+    // lines starting with `;` are skipped
+    Lines,
+}
+
+impl FromStr for InputFormat
+{
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err>
+    {
+        match format
+        {
+            "raw" => Ok(InputFormat::Raw),
+            "bits" => Ok(InputFormat::Bits),
+            "hex" => Ok(InputFormat::Hex),
+            "lines" => Ok(InputFormat::Lines),
+            _other => Err(format!("unknown input format `{}`; expected raw, bits, hex or lines", format)),
+        }
+    }
+}
+
+impl fmt::Display for InputFormat
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            InputFormat::Raw => write!(f, "raw"),
+            InputFormat::Bits => write!(f, "bits"),
+            InputFormat::Hex => write!(f, "hex"),
+            InputFormat::Lines => write!(f, "lines"),
+        }
+    }
+}
+
+// reads `FORMAT:PATH`, or a plain path in the lines format
+pub fn load_input_file(file: &str, order: BitOrder) -> Result<Vec<InputValue>, String>
+{
+    let (format, path) = match file.split_once(':')
+    {
+        Some((format, path)) if format.parse::<InputFormat>().is_ok() => (format.parse::<InputFormat>()?, path),
+        _other => (InputFormat::Lines, file),
+    };
+
+    return read_input_file(path, format, order);
+}
+
+pub fn read_input_file(path: impl AsRef<Path>, format: InputFormat, order: BitOrder) -> Result<Vec<InputValue>, String>
+{
+    let path: &Path = path.as_ref();
+    let bytes: Vec<u8> = fs::read(path)
+        .map_err(|error| format!("could not read input file {}: {}", path.display(), error))?;
+
+    if format == InputFormat::Raw
+    {
+        let bitstring: String = bytes.iter()
+            .map(|byte| format!("{:08b}", byte))
+            .collect::<String>();
+        return Ok(vec![InputValue::from_bitstring(&bitstring, order)?]);
+    }
+
+    let text: String = String::from_utf8(bytes)
+        .map_err(|_| format!("input file {} is not text", path.display()))?;
+
+    match format
+    {
+        InputFormat::Bits =>
+        {
+            let bitstring: String = text.split_whitespace().collect::<String>();
+            Ok(vec![InputValue::from_bitstring(&bitstring, order)?])
+        }
+        InputFormat::Hex =>
+        {
+            let mut bitstring: String = String::new();
+            for digit in text.split_whitespace().collect::<String>().trim_start_matches("0x").chars()
+            {
+                let value: u32 = digit.to_digit(16)
+                    .ok_or(format!("invalid hex digit `{}` in {}", digit, path.display()))?;
+                bitstring.push_str(&format!("{:04b}", value));
+            }
+            Ok(vec![InputValue::from_bitstring(&bitstring, order)?])
+        }
+        _lines => text.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with(";"))
+            .map(|line| InputValue::parse(line, order))
+            .collect::<Result<Vec<InputValue>, String>>(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputValue
+{
+    Number(u32),
+    // bits in index order, so bits[0] is bit 0; the length is exact
+    Bits(BitVec),
+}
+
+impl InputValue
+{
+    // a decimal or `0x` number, or a bitstring written `0b0010` or
+    // `bits:0010` whose leading zeros are kept
+    pub fn parse(text: &str, order: BitOrder) -> Result<Self, String>
+    {
+        if let Some(bitstring) = text.strip_prefix("0b").or(text.strip_prefix("bits:"))
+        {
+            return Self::from_bitstring(bitstring, order);
+        }
+
+        let number: Result<u32, _> = match text.strip_prefix("0x")
+        {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => text.parse::<u32>(),
+        };
+
+        return number.map(InputValue::Number)
+            .map_err(|_| format!("invalid input `{}`; expected a number, 0b... or bits:...", text));
+    }
+
+    pub fn from_bitstring(bitstring: &str, order: BitOrder) -> Result<Self, String>
+    {
+        let mut bits: BitVec = BitVec::new();
+
+        for character in bitstring.chars()
+        {
+            match character
+            {
+                '0' => bits.push(false),
+                '1' => bits.push(true),
+                _other => return Err(format!("invalid bit `{}` in bitstring `{}`", character, bitstring)),
+            }
+        }
+
+        if order == BitOrder::MsbFirst
+        {
+            bits = bits.iter().rev().collect::<BitVec>();
+        }
+
+        return Ok(InputValue::Bits(bits));
+    }
+
+    pub fn len(&self) -> usize
+    {
+        match self
+        {
+            InputValue::Number(number) => number.checked_ilog2().map_or(1, |log| log as usize + 1),
+            InputValue::Bits(bits) => bits.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        return self.len() == 0;
+    }
+}
@@ -0,0 +1,249 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Operand
+{
+    Variable(usize),
+    Bit(usize),
+    Input(usize),
+    Immediate(i64),
+    // the register whose index is held in variable N
+    VariableAt(usize),
+    BitAt(usize),
+    InputAt(usize),
+}
+
+impl Operand
+{
+    pub fn parse(operand: &str) -> Option<Self>
+    {
+        let register = |prefix: &str| operand.strip_prefix(prefix)?.parse::<usize>().ok();
+        let indexed = |prefix: &str| operand.strip_prefix(prefix)?
+            .strip_suffix("]")?
+            .strip_prefix("v")?
+            .parse::<usize>().ok();
+
+        if operand.starts_with("v[")
+        {
+            return indexed("v[").map(Operand::VariableAt);
+        }
+        if operand.starts_with("b[")
+        {
+            return indexed("b[").map(Operand::BitAt);
+        }
+        if operand.starts_with("i[")
+        {
+            return indexed("i[").map(Operand::InputAt);
+        }
+        if operand.starts_with("v")
+        {
+            return register("v").map(Operand::Variable);
+        }
+        if operand.starts_with("b")
+        {
+            return register("b").map(Operand::Bit);
+        }
+        if operand.starts_with("i")
+        {
+            return register("i").map(Operand::Input);
+        }
+        if let Some(hex) = operand.strip_prefix("0x")
+        {
+            return u32::from_str_radix(hex, 16).ok().map(|immediate| Operand::Immediate(immediate as i64));
+        }
+
+        return operand.parse::<i64>().ok().map(Operand::Immediate);
+    }
+
+    pub fn is_register(&self) -> bool
+    {
+        return !matches!(self, Operand::Immediate(_));
+    }
+
+    // the variable holding the index of an indexed operand
+    pub fn index_register(&self) -> Option<Operand>
+    {
+        match self
+        {
+            Operand::VariableAt(index) | Operand::BitAt(index) | Operand::InputAt(index) => Some(Operand::Variable(*index)),
+            _other => None,
+        }
+    }
+}
+
+impl fmt::Display for Operand
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Operand::Variable(index) => write!(f, "v{:02}", index),
+            Operand::Bit(index) => write!(f, "b{:02}", index),
+            Operand::Input(index) => write!(f, "i{:02}", index),
+            Operand::Immediate(value) => write!(f, "{}", value),
+            Operand::VariableAt(index) => write!(f, "v[v{:02}]", index),
+            Operand::BitAt(index) => write!(f, "b[v{:02}]", index),
+            Operand::InputAt(index) => write!(f, "i[v{:02}]", index),
+        }
+    }
+}
+
+pub struct OpcodeInfo
+{
+    pub operator:       &'static str,
+    pub num_operands:   usize,
+    // operands the instruction writes; they are also read if listed in reads
+    pub writes:         &'static [usize],
+    pub reads:          &'static [usize],
+    // operands that must hold a single bit
+    pub bit_operands:   &'static [usize],
+}
+
+pub const OPCODES: [OpcodeInfo; 35] = [
+    OpcodeInfo { operator: "set",  num_operands: 2, writes: &[0], reads: &[1],       bit_operands: &[] },
+    OpcodeInfo { operator: "stl",  num_operands: 2, writes: &[0], reads: &[1],       bit_operands: &[] },
+    OpcodeInfo { operator: "stnb", num_operands: 3, writes: &[0], reads: &[1, 2],    bit_operands: &[] },
+    OpcodeInfo { operator: "iadd", num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "isub", num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "badd", num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[0, 1] },
+    OpcodeInfo { operator: "badc", num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[0, 1] },
+    OpcodeInfo { operator: "bsub", num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[0, 1] },
+    OpcodeInfo { operator: "bsbu", num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[0, 1] },
+    OpcodeInfo { operator: "bsr",  num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "bsl",  num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "clf",  num_operands: 0, writes: &[],  reads: &[],        bit_operands: &[] },
+    OpcodeInfo { operator: "cmp",  num_operands: 2, writes: &[],  reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "jmp",  num_operands: 1, writes: &[],  reads: &[0],       bit_operands: &[] },
+    OpcodeInfo { operator: "jon",  num_operands: 1, writes: &[],  reads: &[],        bit_operands: &[] },
+    OpcodeInfo { operator: "end",  num_operands: 0, writes: &[],  reads: &[],        bit_operands: &[] },
+    OpcodeInfo { operator: "non",  num_operands: 0, writes: &[],  reads: &[],        bit_operands: &[] },
+    OpcodeInfo { operator: "imul", num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "idiv", num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "imod", num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "and",  num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "or",   num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "xor",  num_operands: 2, writes: &[0], reads: &[0, 1],    bit_operands: &[] },
+    OpcodeInfo { operator: "not",  num_operands: 1, writes: &[0], reads: &[0],       bit_operands: &[] },
+    OpcodeInfo { operator: "snb",  num_operands: 3, writes: &[0], reads: &[0, 1, 2], bit_operands: &[1] },
+    OpcodeInfo { operator: "stf",  num_operands: 2, writes: &[0], reads: &[],        bit_operands: &[] },
+    OpcodeInfo { operator: "left", num_operands: 1, writes: &[],  reads: &[0],       bit_operands: &[] },
+    OpcodeInfo { operator: "right", num_operands: 1, writes: &[], reads: &[0],       bit_operands: &[] },
+    OpcodeInfo { operator: "rew",  num_operands: 1, writes: &[],  reads: &[0],       bit_operands: &[] },
+    OpcodeInfo { operator: "read", num_operands: 2, writes: &[0], reads: &[1],       bit_operands: &[] },
+    OpcodeInfo { operator: "acc",  num_operands: 0, writes: &[],  reads: &[],        bit_operands: &[] },
+    OpcodeInfo { operator: "rej",  num_operands: 0, writes: &[],  reads: &[],        bit_operands: &[] },
+    OpcodeInfo { operator: "out",  num_operands: 1, writes: &[],  reads: &[0],       bit_operands: &[] },
+    OpcodeInfo { operator: "guess", num_operands: 1, writes: &[0], reads: &[],        bit_operands: &[] },
+    OpcodeInfo { operator: "rand", num_operands: 1, writes: &[0], reads: &[],        bit_operands: &[] },
+];
+
+pub const CONDITIONS: [&str; 11] = ["e", "ne", "g", "ge", "l", "le", "c", "nc", "b", "nb", ""];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction
+{
+    pub operator:   String,
+    pub operands:   Vec<String>,
+}
+
+impl Instruction
+{
+    pub fn parse(instruction: &str) -> Self
+    {
+        let mut parts = instruction.split_whitespace();
+
+        Self
+        {
+            operator:   parts.next().unwrap_or("").to_string(),
+            operands:   parts.map(|part| part.to_string()).collect::<Vec<String>>(),
+        }
+    }
+
+    pub fn info(&self) -> Option<&'static OpcodeInfo>
+    {
+        return OPCODES.iter().find(|info| info.operator == self.operator);
+    }
+
+    pub fn operand(&self, index: usize) -> &str
+    {
+        return self.operands.get(index).map_or("", |operand| operand.as_str());
+    }
+
+    // the operand holding a `jon` condition rather than a register or value
+    pub fn condition_operand(&self) -> Option<usize>
+    {
+        match self.operator.as_str()
+        {
+            "jon" => Some(0),
+            "stf" => Some(1),
+            _other => None,
+        }
+    }
+
+    pub fn written_operands(&self) -> Vec<Operand>
+    {
+        return self.info().map_or(Vec::new(), |info| info.writes.iter()
+            .filter_map(|index| Operand::parse(self.operand(*index)))
+            .collect::<Vec<Operand>>());
+    }
+
+    // includes the index variables of indexed operands, even written ones
+    pub fn read_operands(&self) -> Vec<Operand>
+    {
+        return self.info().map_or(Vec::new(), |info| info.reads.iter()
+            .filter_map(|index| Operand::parse(self.operand(*index)))
+            .chain(info.writes.iter()
+                .chain(info.reads.iter())
+                .filter_map(|index| Operand::parse(self.operand(*index))?.index_register()))
+            .collect::<Vec<Operand>>());
+    }
+
+    pub fn jump_target(&self) -> Option<usize>
+    {
+        if self.operator != "jmp"
+        {
+            return None;
+        }
+
+        return match Operand::parse(self.operand(0))
+        {
+            Some(Operand::Immediate(target)) => usize::try_from(target).ok(),
+            _other => None,
+        };
+    }
+
+    // lines that may run after this one; a line equal to the program
+    // length means execution runs off the end
+    pub fn successors(&self, line_number: usize) -> Vec<usize>
+    {
+        match self.operator.as_str()
+        {
+            "jmp" => self.jump_target().into_iter().collect::<Vec<usize>>(),
+            "jon" => vec![line_number + 1, line_number + 2],
+            "end" | "acc" | "rej" => Vec::new(),
+            _other => vec![line_number + 1],
+        }
+    }
+}
+
+impl fmt::Display for Instruction
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}", self.operator)?;
+
+        for operand in self.operands.iter()
+        {
+            write!(f, " {}", operand)?;
+        }
+
+        return Ok(());
+    }
+}
+
+pub fn decode_program(program_lines: &[String]) -> Vec<Instruction>
+{
+    return program_lines.iter()
+        .map(|line| Instruction::parse(line))
+        .collect::<Vec<Instruction>>();
+}
@@ -1,12 +1,112 @@
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader},
+    collections::VecDeque,
+    fs,
     path::Path,
 };
 
 use bit_vec::BitVec;
 
-const NUM_FLAGS: usize = 4;
+mod access;
+mod arithmetic;
+mod bounds;
+mod bytecode;
+mod cfg;
+mod compose;
+mod fault;
+mod history;
+mod input;
+mod instruction;
+mod lint;
+mod monte_carlo;
+mod outcome;
+mod program;
+mod random;
+mod report;
+mod search;
+mod snapshot;
+mod turing;
+
+pub use access::{
+    Head,
+    InputAccess,
+};
+pub use arithmetic::ArithmeticMode;
+pub use bounds::{
+    estimate_space,
+    Bound,
+    SpaceClass,
+    SpaceEstimate,
+};
+pub use bytecode::{
+    Bytecode,
+    BYTECODE_MAGIC,
+    BYTECODE_VERSION,
+    ISA_VERSION,
+};
+pub use compose::{
+    compose,
+    CompositionReport,
+    RecomputedInput,
+};
+pub use cfg::{
+    BasicBlock,
+    ControlFlowGraph,
+    Edge,
+    EdgeKind,
+    Profile,
+};
+pub use fault::Fault;
+pub use history::UndoRecord;
+pub use input::{
+    load_input_file,
+    read_input_file,
+    BitOrder,
+    InputFormat,
+    InputValue,
+};
+pub use instruction::{
+    decode_program,
+    Instruction,
+    OpcodeInfo,
+    Operand,
+    CONDITIONS,
+    OPCODES,
+};
+pub use lint::{
+    lint_program,
+    Diagnostic,
+    Severity,
+};
+pub use monte_carlo::{
+    monte_carlo,
+    Distribution,
+    MonteCarloReport,
+};
+pub use outcome::Outcome;
+pub use program::Program;
+pub use random::Rng;
+pub use report::{
+    RegisterReport,
+    RunConfig,
+    RunReport,
+    SpaceUsage,
+};
+pub use snapshot::SNAPSHOT_HEADER;
+pub use search::{
+    search,
+    Configuration,
+    SearchOrder,
+    SearchReport,
+};
+pub use turing::{
+    Move,
+    Tape,
+    Transition,
+    TuringMachine,
+    BLANK,
+};
+
+pub const NUM_FLAGS: usize = 4;
 const EQUAL_FLAG: usize = 0;
 const GREATER_FLAG: usize = 1;
 const CARRY_FLAG: usize = 2;
@@ -25,6 +125,8 @@ pub struct Variable
 {
     pub value:      BitVec,
     pub max_size:   usize,
+    // loaded as a bitstring, so its length and leading zeros are part of it
+    pub exact:      bool,
 }
 
 impl Variable
@@ -37,9 +139,27 @@ impl Variable
         {
             value: bits.clone(),
             max_size: bits.len(),
+            exact: false,
         }
     }
 
+    fn from_bits(bits: BitVec) -> Self
+    {
+        Self
+        {
+            max_size: bits.len(),
+            value: bits,
+            exact: false,
+        }
+    }
+
+    fn set_bits(&mut self, bits: BitVec)
+    {
+        self.max_size = self.max_size.max(bits.len());
+        self.value = bits;
+        self.exact = false;
+    }
+
     fn set_value(&mut self, uint: u32)
     {
         let bits: BitVec = Self::u32_to_bits(uint);
@@ -61,7 +181,7 @@ impl Variable
     #[allow(dead_code)]
     pub fn u32_to_bits(uint: u32) -> BitVec
     {
-        let num_bits: usize = ((uint as i32).checked_ilog2().unwrap_or(0) + 1) as usize;
+        let num_bits: usize = (uint.checked_ilog2().unwrap_or(0) + 1) as usize;
         let mut bits: BitVec = BitVec::from_elem(num_bits, false);
 
         for i in 0..num_bits
@@ -98,6 +218,45 @@ pub struct Device
     pub has_loaded_input:       bool,
     pub device_state:           DeviceState,
     pub instructions_executed:  u32,
+    pub record_profile:         bool,
+    pub profile:                Profile,
+    pub fault:                  Option<Fault>,
+    // set by `acc` (true) and `rej` (false)
+    pub verdict:                Option<bool>,
+    // set before loading inputs, which are stored in the mode's representation
+    pub arithmetic:             ArithmeticMode,
+    pub input_access:           InputAccess,
+    pub heads:                  Vec<Head>,
+    // write-only: `out` appends to it, the host reads it, and it is not
+    // execution space
+    pub output:                 BitVec,
+    // when set, `out` keeps only this output bit, in captured_bit, and stops
+    // the program once it is written
+    pub capture_output:         Option<usize>,
+    pub captured_bit:           Option<bool>,
+    pub output_written:         usize,
+    // i00 as the output of another program; see compose
+    pub recomputed:             Option<Box<RecomputedInput>>,
+    // the bits `guess` writes, in order; once they run out a search pauses
+    // at the next `guess`, and any other run guesses 0
+    pub guesses:                Vec<bool>,
+    pub guesses_used:           usize,
+    pub pause_on_guess:         bool,
+    pub awaiting_guess:         bool,
+    // `rand` draws from rng, which every run restarts from seed
+    pub seed:                   u64,
+    pub rng:                    Rng,
+    // stops a run once this many instructions have run, still running, so
+    // that it can be saved and resumed
+    pub pause_at:               Option<u32>,
+    // what each of the last history_limit instructions changed, newest last;
+    // recorded only when history_limit is above 0
+    pub history:                VecDeque<UndoRecord>,
+    pub history_limit:          usize,
+    // every distinct warning raised, printed to stderr as well unless
+    // print_warnings is off
+    pub warnings:               Vec<String>,
+    pub print_warnings:         bool,
 }
 
 impl Device
@@ -116,22 +275,58 @@ impl Device
             has_loaded_input:       false,
             device_state:           DeviceState::IdlePhase,
             instructions_executed:  0,
+            record_profile:         false,
+            profile:                Profile::default(),
+            fault:                  None,
+            verdict:                None,
+            arithmetic:             ArithmeticMode::default(),
+            input_access:           InputAccess::default(),
+            heads:                  Vec::new(),
+            output:                 BitVec::new(),
+            capture_output:         None,
+            captured_bit:           None,
+            output_written:         0,
+            recomputed:             None,
+            guesses:                Vec::new(),
+            guesses_used:           0,
+            pause_on_guess:         false,
+            awaiting_guess:         false,
+            seed:                   0,
+            rng:                    Rng::new(0),
+            pause_at:               None,
+            history:                VecDeque::new(),
+            history_limit:          0,
+            warnings:               Vec::new(),
+            print_warnings:         true,
         }
     }
 
-    fn decipher_immediate(&mut self, immediate_string: &str) -> u32
+    fn warn(&mut self, message: &str)
+    {
+        if self.print_warnings
+        {
+            eprintln!("WARNING: {}", message);
+        }
+
+        if !self.warnings.iter().any(|warning| warning == message)
+        {
+            self.warnings.push(message.to_string());
+        }
+    }
+
+    fn decipher_immediate(&mut self, immediate_string: &str) -> i64
     {
         // hex encoded
         if immediate_string.starts_with("0x")
         {
             let immediate_wo_prefix: &str = immediate_string.trim_start_matches("0x");
             let immediate_as_u32: u32 = u32::from_str_radix(immediate_wo_prefix, 16).unwrap();
-            return immediate_as_u32;
+            return immediate_as_u32 as i64;
         }
 
         // otherwise is int
-        let immediate_as_u32: u32 = immediate_string.parse::<i64>().unwrap() as u32;
-        return immediate_as_u32;
+        let immediate_as_i64: i64 = immediate_string.parse::<i64>().unwrap();
+        return immediate_as_i64;
     }
 
     fn check_flow_condition(&mut self, condition: &str) -> bool
@@ -164,29 +359,33 @@ impl Device
         }
     }
 
-    pub fn get_source_value(&mut self, operand: &str) -> u32
+    // the value of an operand as a number; registers hold values already in
+    // range, immediates are taken as written
+    fn source_number(&mut self, operand: &str) -> i64
     {
-        let source: u32;
+        let operand: &str = &self.resolve_operand(operand, false)
+            .unwrap_or_else(|fault| panic!("{}", fault));
+        let source: i64;
 
         // value from variable
         if operand.starts_with("v")
         {
             let variable_index: usize = operand.trim_start_matches("v").parse::<usize>().unwrap();
-            source = self.write_variables[variable_index].get_value();
+            source = self.arithmetic.from_bits(&self.write_variables[variable_index].value);
         }
         
         // value from bit variable
         else if operand.starts_with("b")
         {
             let bit_index: usize = operand.trim_start_matches("b").parse::<usize>().unwrap();
-            source = self.write_bits[bit_index].get_value();
+            source = self.write_bits[bit_index].get_value() as i64;
         }
         
         // value from input variable
         else if operand.starts_with("i")
         {
             let input_index: usize = operand.trim_start_matches("i").parse::<usize>().unwrap();
-            source = self.input_variables[input_index].get_value();
+            source = self.arithmetic.from_bits(&self.input_variables[input_index].value);
         }
         
         // immediate value
@@ -198,24 +397,70 @@ impl Device
         return source;
     }
 
+    // the bits `stl` and `stnb` see: a bitstring input as loaded, otherwise
+    // the shortest form of the value
+    fn source_bits(&mut self, operand: &str) -> BitVec
+    {
+        if let Some(input_index) = operand.strip_prefix("i").and_then(|index| index.parse::<usize>().ok())
+        {
+            if self.input_variables[input_index].exact
+            {
+                return self.input_variables[input_index].value.clone();
+            }
+        }
+
+        return Variable::u32_to_bits(self.get_source_value(operand));
+    }
+
+    // bit operations see negative values as 32-bit two's complement
+    pub fn get_source_value(&mut self, operand: &str) -> u32
+    {
+        return self.source_number(operand) as u32;
+    }
+
+    fn integer_value(&mut self, operand: &str) -> Result<i64, Fault>
+    {
+        let source: i64 = self.source_number(operand);
+
+        return self.arithmetic.normalize(source as i128, self.instruction_pointer);
+    }
+
+    fn set_integer_destination(&mut self, operand: &str, value: i128) -> Result<(), Fault>
+    {
+        let value: i64 = self.arithmetic.normalize(value, self.instruction_pointer)?;
+
+        self.store_number(operand, value);
+
+        return Ok(());
+    }
+
     pub fn set_destination(&mut self, operand: &str, value: u32)
     {
+        self.store_number(operand, value as i64);
+    }
+
+    fn store_number(&mut self, operand: &str, value: i64)
+    {
+        let operand: &str = &self.resolve_operand(operand, true)
+            .unwrap_or_else(|fault| panic!("{}", fault));
+
         // value from variable
         if operand.starts_with("v")
         {
             if self.device_state != DeviceState::ExecutionPhase
             {
-                eprintln!("WARNING: setting variable not in execution phase, value will be erased in execution phase");
+                self.warn("setting variable not in execution phase, value will be erased in execution phase");
             }
             
+            let bits: BitVec = self.arithmetic.to_bits(value);
             let variable_index: usize = operand.trim_start_matches("v").parse::<usize>().unwrap();
             if variable_index == self.write_variables.len()
             {
-                let new_variable = Variable::new(value);
+                let new_variable = Variable::from_bits(bits);
                 self.write_variables.push(new_variable);
             } else
             {
-                self.write_variables[variable_index].set_value(value);
+                self.write_variables[variable_index].set_bits(bits);
             }
         }
         
@@ -224,10 +469,10 @@ impl Device
         {
             if self.device_state != DeviceState::ExecutionPhase
             {
-                eprintln!("WARNING: setting bit variable not in execution phase; value will be erased in execution phase");
+                self.warn("setting bit variable not in execution phase; value will be erased in execution phase");
             }
 
-            if value > 1
+            if !(0..=1).contains(&value)
             {
                 panic!("Invalid bit value: {}", value)
             }
@@ -235,11 +480,11 @@ impl Device
             let bit_index: usize = operand.trim_start_matches("b").parse::<usize>().unwrap();
             if bit_index == self.write_bits.len()
             {
-                let new_variable = Variable::new(value);
+                let new_variable = Variable::new(value as u32);
                 self.write_bits.push(new_variable);
             } else
             {
-                self.write_bits[bit_index].set_value(value);
+                self.write_bits[bit_index].set_value(value as u32);
             }
         }
         
@@ -252,14 +497,15 @@ impl Device
                 panic!("Invalid assignment: cannot set input variable outside of input phase");
             }
 
+            let bits: BitVec = self.arithmetic.to_bits(value);
             let input_index: usize = operand.trim_start_matches("i").parse::<usize>().unwrap();
             if input_index == self.input_variables.len()
             {
-                let new_variable = Variable::new(value);
+                let new_variable = Variable::from_bits(bits);
                 self.input_variables.push(new_variable);
             } else
             {
-                self.input_variables[input_index].set_value(value);
+                self.input_variables[input_index].set_bits(bits);
             }
         }
         
@@ -270,81 +516,86 @@ impl Device
         } 
     }
 
-    pub fn execute_instruction(&mut self, instruction: &str)
+    // turns `v[vK]`, `b[vK]` and `i[vK]` into the register vK points at; a
+    // write may also create the next register
+    fn resolve_operand(&mut self, operand: &str, writing: bool) -> Result<String, Fault>
     {
-        let mut instruction_parts: Vec<&str> = instruction.split_whitespace()
-            .collect::<Vec<&str>>();
+        let (prefix, index_operand) = match operand.split_once('[')
+        {
+            Some((prefix, rest)) if rest.ends_with(']') => (prefix, rest.trim_end_matches(']')),
+            _other => return Ok(operand.to_string()),
+        };
 
-        // push empty values to ensure have 1 operators + 3 operands
-        instruction_parts.push("");
-        instruction_parts.push("");
-        instruction_parts.push("");
-        instruction_parts.push("");
-        let operator: &str = instruction_parts[0].trim();
-        let operand1: &str = instruction_parts[1].trim();
-        let operand2: &str = instruction_parts[2].trim();
-        let operand3: &str = instruction_parts[3].trim();
+        let index: usize = self.get_source_value(index_operand) as usize;
+        let num_registers: usize = match prefix
+        {
+            "v" => self.write_variables.len(),
+            "b" => self.write_bits.len(),
+            "i" => self.input_variables.len(),
+            _other => panic!("Unknown location in memory: {}", operand),
+        };
 
-        let mut increment_instruction_pointer: bool = true;
+        let register: String = format!("{prefix}{index}");
 
-        match operator
+        if index > num_registers || (index == num_registers && !writing)
         {
-            // ASSIGNMENT OPERATORS
-            "set" =>
-            {
-                let source: u32 = self.get_source_value(operand2);
+            return Err(Fault::IndexOutOfRange { line: self.instruction_pointer, operand: operand.to_string(), register });
+        }
 
-                self.set_destination(operand1, source);
-            }
-            "stl" =>
-            {
-                let source: u32 = self.get_source_value(operand2);
-                let source_length: usize = Variable::u32_to_bits(source).len();
+        return Ok(register);
+    }
 
-                self.set_destination(operand1, source_length as u32);
-            }
-            "stnb" =>
-            {
-                let source: u32 = self.get_source_value(operand2);
-                
-                let n: u32 = self.get_source_value(operand3);
+    fn execute_integer_instruction(&mut self, operator: &str, operand1: &str, operand2: &str) -> Result<(), Fault>
+    {
+        let source: i128 = self.integer_value(operand2)? as i128;
 
-                let source_as_bits = Variable::u32_to_bits(source);
+        if operator == "set"
+        {
+            return self.set_integer_destination(operand1, source);
+        }
 
-                let nth_bit : u32;
-                if n as usize >= source_as_bits.len()
-                {
-                    nth_bit = 0;
-                } else
-                {
-                    nth_bit = source_as_bits[n as usize] as u32;
-                }
-            
-                self.set_destination(operand1, nth_bit);
-            }
-            // INTEGER OPERATION OPERATORS
-            "iadd" =>
-            {
-                let source: u32 = self.get_source_value(operand2);
+        let destination: i128 = self.integer_value(operand1)? as i128;
 
-                let destination: u32 = self.get_source_value(operand1);
+        if operator == "cmp"
+        {
+            self.flags[EQUAL_FLAG] = destination == source;
+            self.flags[GREATER_FLAG] = destination > source;
+            return Ok(());
+        }
 
-                let new_value: u32 = source + destination;
+        if (operator == "idiv" || operator == "imod") && source == 0
+        {
+            return Err(Fault::DivisionByZero { line: self.instruction_pointer });
+        }
 
-                self.set_destination(operand1, new_value); 
-            }
-            "isub" =>
-            {
-                let source: u32 = self.get_source_value(operand2);
+        let new_value: i128 = match operator
+        {
+            "iadd" => destination + source,
+            "isub" => destination - source,
+            "imul" => destination * source,
+            "idiv" => destination / source,
+            _imod => destination % source,
+        };
+
+        return self.set_integer_destination(operand1, new_value);
+    }
 
-                let destination: u32 = self.get_source_value(operand1);
+    // a 32-bit two's complement result as a number of the arithmetic mode
+    fn pattern_value(&self, pattern: u32) -> i128
+    {
+        if self.arithmetic.is_signed()
+        {
+            return pattern as i32 as i128;
+        }
 
-                let new_value: u32 = destination - source;
+        return pattern as i128;
+    }
 
-                self.set_destination(operand1, new_value); 
-            }
-            // BINARY OPERATION OPERATORS
-            "badd" =>
+    fn execute_bit_instruction(&mut self, operator: &str, operand1: &str, operand2: &str, operand3: &str) -> Result<(), Fault>
+    {
+        let new_value: i128 = match operator
+        {
+            "badd" | "badc" | "bsub" | "bsbu" =>
             {
                 let source: u32 = self.get_source_value(operand2);
 
@@ -355,128 +606,216 @@ impl Device
 
                 let destination: u32 = self.get_source_value(operand1);
 
-
                 if destination > 1
                 {
                     panic!("Invalid value for binary addition: {} at {}", destination, operand1);
                 }
 
-                let mut new_value: u32 = source + destination;
+                match operator
+                {
+                    "badd" | "badc" =>
+                    {
+                        let carry: u32 = (operator == "badc" && self.flags[CARRY_FLAG]) as u32;
+                        let new_value: u32 = source + destination + carry;
 
-                self.flags[CARRY_FLAG] = new_value >= 2;
+                        self.flags[CARRY_FLAG] = new_value >= 2;
 
-                if self.flags[CARRY_FLAG]
-                {
-                    new_value -= 2;
-                }
+                        (new_value % 2) as i128
+                    }
+                    _subtract =>
+                    {
+                        let borrow: i32 = (operator == "bsbu" && self.flags[BORROW_FLAG]) as i32;
+                        let new_value: i32 = (source as i32) - (destination as i32) - borrow;
 
-                self.set_destination(operand1, new_value);
+                        self.flags[BORROW_FLAG] = new_value < 0;
+
+                        new_value.rem_euclid(2) as i128
+                    }
+                }
             }
-            "badc" =>
+            // shifts multiply and divide by powers of two, rounding down, so
+            // they leave the range like any other integer operation
+            "bsr" | "bsl" =>
             {
-                let source: u32 = self.get_source_value(operand2);
+                let shift: u32 = self.get_source_value(operand2).min(96);
+                let destination: i128 = self.source_number(operand1) as i128;
 
-                if source > 1
+                match operator
                 {
-                    panic!("Invalid value for binary addition: {} at {}", source, operand2);
+                    "bsl" => destination << shift,
+                    _bsr => destination >> shift,
                 }
-
+            }
+            "and" | "or" | "xor" =>
+            {
+                let source: u32 = self.get_source_value(operand2);
                 let destination: u32 = self.get_source_value(operand1);
 
-
-                if destination > 1
+                self.pattern_value(match operator
                 {
-                    panic!("Invalid value for binary addition: {} at {}", destination, operand1);
-                }
-
-                let mut new_value: u32 = source + destination + (self.flags[CARRY_FLAG] as u32);
-
-                self.flags[CARRY_FLAG] = new_value >= 2;
+                    "and" => destination & source,
+                    "or" => destination | source,
+                    _xor => destination ^ source,
+                })
+            }
+            "not" =>
+            {
+                let destination: u32 = self.get_source_value(operand1);
 
-                if self.flags[CARRY_FLAG]
-                {
-                    new_value -= 2;
-                }
+                // flips the bits the value has, so a bit stays a bit
+                let width: usize = Variable::u32_to_bits(destination).len();
 
-                self.set_destination(operand1, new_value);
+                self.pattern_value(!destination & (u32::MAX >> (32 - width)))
             }
-            "bsub" =>
+            _snb =>
             {
                 let source: u32 = self.get_source_value(operand2);
 
                 if source > 1
                 {
-                    panic!("Invalid value for binary addition: {} at {}", source, operand2);
+                    panic!("Invalid bit value: {} at {}", source, operand2);
                 }
 
-                let destination: u32 = self.get_source_value(operand1);
-
+                let n: u32 = self.get_source_value(operand3);
 
-                if destination > 1
+                if n >= u32::BITS
                 {
-                    panic!("Invalid value for binary addition: {} at {}", destination, operand1);
+                    panic!("Invalid bit index: {} at {}", n, operand3);
                 }
 
-                let mut new_value: i32 = (source as i32) -  (destination as i32);
-
-                self.flags[BORROW_FLAG] =  new_value < 0;
-
-                if new_value < 0
-                {
-                    if new_value < -2 {panic!("Invalid binary subtraction")}
-                    new_value += 2;
-                }
+                let destination: u32 = self.get_source_value(operand1);
 
-                self.set_destination(operand1, new_value as u32);
+                self.pattern_value((destination & !(1 << n)) | (source << n))
             }
-            "bsbu" =>
-            {
-                let source: u32 = self.get_source_value(operand2);
+        };
 
-                if source > 1
-                {
-                    panic!("Invalid value for binary addition: {} at {}", source, operand2);
-                }
+        return self.set_integer_destination(operand1, new_value);
+    }
 
-                let destination: u32 = self.get_source_value(operand1);
+    pub fn execute_instruction(&mut self, instruction: &str)
+    {
+        let mut instruction_parts: Vec<&str> = instruction.split_whitespace()
+            .collect::<Vec<&str>>();
+
+        // push empty values to ensure have 1 operators + 3 operands
+        instruction_parts.push("");
+        instruction_parts.push("");
+        instruction_parts.push("");
+        instruction_parts.push("");
+        let operator: &str = instruction_parts[0].trim();
 
+        // indexed operands are resolved up front so that a bad index is a
+        // fault before anything is written
+        let info: Option<&OpcodeInfo> = OPCODES.iter().find(|info| info.operator == operator);
+        let mut operands: Vec<String> = Vec::new();
+        for (index, operand) in instruction_parts[1..4].iter().enumerate()
+        {
+            let writing: bool = info.is_some_and(|info| info.writes.contains(&index) && !info.reads.contains(&index));
 
-                if destination > 1
+            match self.resolve_operand(operand.trim(), writing)
+            {
+                Ok(operand) => operands.push(operand),
+                Err(fault) =>
                 {
-                    panic!("Invalid value for binary addition: {} at {}", destination, operand1);
+                    self.raise_fault(fault);
+                    return;
                 }
+            }
+        }
+        let operand1: &str = &operands[0];
+        let operand2: &str = &operands[1];
+        let operand3: &str = &operands[2];
 
-                let mut new_value: i32 = (source as i32) -  (destination as i32) - (self.flags[BORROW_FLAG] as i32);
+        if let Err(fault) = self.check_input_access(operator, info, &operands)
+        {
+            self.raise_fault(fault);
+            return;
+        }
 
-                self.flags[BORROW_FLAG] =  new_value < 0;
+        let mut increment_instruction_pointer: bool = true;
 
-                if new_value < 0
+        match operator
+        {
+            // ASSIGNMENT OPERATORS
+            "set" =>
+            {
+                if let Err(fault) = self.execute_integer_instruction(operator, operand1, operand2)
                 {
-                    if new_value < -2 {panic!("Invalid binary subtraction")}
-                    new_value += 2;
+                    self.raise_fault(fault);
+                    return;
                 }
-
-                self.set_destination(operand1, new_value as u32);
             }
-            "bsr" =>
+            "stl" =>
             {
-                let source: u32 = self.get_source_value(operand2);
+                let source_length: usize = self.source_bits(operand2).len();
 
-                let destination: u32 = self.get_source_value(operand1);
-
-                let new_value: u32 = destination >> source;
-
-                self.set_destination(operand1, new_value); 
+                self.set_destination(operand1, source_length as u32);
             }
-            "bsl" =>
+            "stnb" =>
             {
-                let source: u32 = self.get_source_value(operand2);
+                let n: u32 = self.get_source_value(operand3);
 
-                let destination: u32 = self.get_source_value(operand1);
+                let source_as_bits: BitVec = self.source_bits(operand2);
 
-                let new_value: u32 = destination << source;
+                let nth_bit : u32;
+                if n as usize >= source_as_bits.len()
+                {
+                    nth_bit = 0;
+                } else
+                {
+                    nth_bit = source_as_bits[n as usize] as u32;
+                }
+            
+                self.set_destination(operand1, nth_bit);
+            }
+            // INTEGER OPERATION OPERATORS
+            "iadd" | "isub" | "imul" | "idiv" | "imod" =>
+            {
+                if let Err(fault) = self.execute_integer_instruction(operator, operand1, operand2)
+                {
+                    self.raise_fault(fault);
+                    return;
+                }
+            }
+            // BINARY OPERATION OPERATORS
+            "badd" | "badc" | "bsub" | "bsbu" | "bsr" | "bsl" | "and" | "or" | "xor" | "not" | "snb" =>
+            {
+                if let Err(fault) = self.execute_bit_instruction(operator, operand1, operand2, operand3)
+                {
+                    self.raise_fault(fault);
+                    return;
+                }
+            }
+            "stf" =>
+            {
+                let flag: bool = self.check_flow_condition(operand2);
 
-                self.set_destination(operand1, new_value); 
+                self.set_destination(operand1, flag as u32);
+            }
+            // INPUT HEAD OPERATORS
+            "left" | "right" | "rew" =>
+            {
+                if let Err(fault) = self.move_head(operator, operand1)
+                {
+                    self.raise_fault(fault);
+                    return;
+                }
+            }
+            "read" =>
+            {
+                let input_index: usize = operand2.trim_start_matches("i").parse::<usize>().unwrap();
+                let input_length: usize = self.input_length(input_index);
+                let head: &mut Head = self.head(input_index);
+                head.used = true;
+                head.passes = head.passes.max(1);
+                let position: isize = head.position;
+
+                // an end marker reads as 0 and sets EQUAL
+                let on_marker: bool = position < 0 || position as usize >= input_length;
+                self.flags[EQUAL_FLAG] = on_marker;
+
+                let bit: u32 = if on_marker { 0 } else { self.input_bit(input_index, position as usize) as u32 };
+                self.set_destination(operand1, bit);
             }
             // COMPARISON OPERATORS
             "clf" =>
@@ -488,12 +827,11 @@ impl Device
             }
             "cmp" =>
             {
-                let a_value: u32 = self.get_source_value(operand1);
-                let b_value: u32 = self.get_source_value(operand2);
-
-
-                self.flags[EQUAL_FLAG] = a_value == b_value;
-                self.flags[GREATER_FLAG] = a_value > b_value;
+                if let Err(fault) = self.execute_integer_instruction(operator, operand1, operand2)
+                {
+                    self.raise_fault(fault);
+                    return;
+                }
             }
             // PROGRAM FLOW OPERATORS
             "jmp" =>
@@ -512,10 +850,55 @@ impl Device
 
                 self.instruction_pointer += 1;
             }
+            "guess" =>
+            {
+                match self.guesses.get(self.guesses_used).copied()
+                {
+                    Some(guess) =>
+                    {
+                        self.guesses_used += 1;
+                        self.set_destination(operand1, guess as u32);
+                    }
+                    // the search runs this line again once it picks a bit
+                    None if self.pause_on_guess =>
+                    {
+                        self.awaiting_guess = true;
+                        self.program_running = false;
+                        return;
+                    }
+                    None => self.set_destination(operand1, 0),
+                }
+            }
+            "rand" =>
+            {
+                let bit: bool = self.rng.next_bit();
+                self.set_destination(operand1, bit as u32);
+            }
+            "out" =>
+            {
+                let bits: BitVec = self.source_bits(operand1);
+
+                match self.capture_output
+                {
+                    Some(position) if (self.output_written..self.output_written + bits.len()).contains(&position) =>
+                    {
+                        self.captured_bit = Some(bits[position - self.output_written]);
+                        self.program_running = false;
+                    }
+                    Some(_) => {}
+                    None => self.output.extend(bits.iter()),
+                }
+                self.output_written += bits.len();
+            }
             "end" =>
             {
                 self.program_running = false;
             }
+            "acc" | "rej" =>
+            {
+                self.verdict = Some(operator == "acc");
+                self.program_running = false;
+            }
             "non"=>{}
             _other =>
             {
@@ -529,12 +912,127 @@ impl Device
 
     }
 
+    fn head(&mut self, input_index: usize) -> &mut Head
+    {
+        if self.heads.len() <= input_index
+        {
+            self.heads.resize(input_index + 1, Head::new());
+        }
+
+        return &mut self.heads[input_index];
+    }
+
+    fn input_length(&mut self, input_index: usize) -> usize
+    {
+        match &self.recomputed
+        {
+            Some(recomputed) if input_index == 0 => recomputed.length,
+            _other => self.source_bits(&format!("i{:02}", input_index)).len(),
+        }
+    }
+
+    fn input_bit(&mut self, input_index: usize, position: usize) -> bool
+    {
+        match &mut self.recomputed
+        {
+            Some(recomputed) if input_index == 0 => recomputed.bit(position),
+            _other => self.source_bits(&format!("i{:02}", input_index))[position],
+        }
+    }
+
+    fn check_input_access(&mut self, operator: &str, info: Option<&OpcodeInfo>, operands: &[String]) -> Result<(), Fault>
+    {
+        let line: usize = self.instruction_pointer;
+        let is_head_operator: bool = matches!(operator, "left" | "right" | "rew" | "read");
+
+        if self.input_access == InputAccess::RandomAccess
+        {
+            if is_head_operator
+            {
+                return Err(Fault::InputAccess { line, reason: "input heads need two-way or streaming input access".to_string() });
+            }
+            return Ok(());
+        }
+
+        if operator == "left" && self.input_access == InputAccess::Streaming
+        {
+            return Err(Fault::InputAccess { line, reason: "a streaming head cannot move left".to_string() });
+        }
+
+        if is_head_operator
+        {
+            return Ok(());
+        }
+
+        let reads_input: bool = info.is_some_and(|info| info.reads.iter()
+            .any(|index| matches!(Operand::parse(&operands[*index]), Some(Operand::Input(_)))));
+
+        if reads_input
+        {
+            return Err(Fault::InputAccess { line, reason: "reads an input directly; only head instructions may read inputs outside random access".to_string() });
+        }
+
+        return Ok(());
+    }
+
+    fn move_head(&mut self, operator: &str, operand: &str) -> Result<(), Fault>
+    {
+        let line: usize = self.instruction_pointer;
+        let input_index: usize = operand.trim_start_matches("i").parse::<usize>().unwrap();
+        let input_length: isize = self.input_length(input_index) as isize;
+        let head: &mut Head = self.head(input_index);
+
+        head.used = true;
+        head.passes = head.passes.max(1);
+
+        match operator
+        {
+            "left" if head.position < 0 => return Err(Fault::HeadOffTape { line, input: input_index }),
+            "right" if head.position >= input_length => return Err(Fault::HeadOffTape { line, input: input_index }),
+            "left" => head.position -= 1,
+            "right" => head.position += 1,
+            _rew =>
+            {
+                if head.position != 0
+                {
+                    head.passes += 1;
+                }
+                head.position = 0;
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn raise_fault(&mut self, fault: Fault)
+    {
+        self.fault = Some(fault);
+        self.program_running = false;
+    }
+
     pub fn load_program(&mut self, file_path: impl AsRef<Path>)
     {
-        let file: File = File::open(file_path).expect("Could not open file");
-        let io_file_lines:io::Result<Vec<String>> = BufReader::new(file).lines().collect();
-        let program_lines = io_file_lines.expect("Could not read program lines");
-        self.program_lines = program_lines.into_iter().filter(|x| !x.starts_with(";")).collect::<Vec<String>>().clone();
+        let bytes: Vec<u8> = fs::read(file_path).expect("Could not open file");
+
+        if Bytecode::is_bytecode(&bytes)
+        {
+            let bytecode: Bytecode = Bytecode::decode(&bytes)
+                .unwrap_or_else(|error| panic!("Could not load bytecode: {}", error));
+            self.load_bytecode(&bytecode);
+            return;
+        }
+
+        let program_text: String = String::from_utf8(bytes).expect("Could not read program lines");
+        self.program_lines = program::instruction_lines(&program_text)
+            .map(|(_, line)| line.to_string())
+            .collect::<Vec<String>>();
+    }
+
+    pub fn load_bytecode(&mut self, bytecode: &Bytecode)
+    {
+        self.program_lines = bytecode.program.iter()
+            .map(|instruction| instruction.to_string())
+            .collect::<Vec<String>>();
     }
 
     // FUNCTIONS FOR PROGRAM EXECUTION
@@ -542,20 +1040,63 @@ impl Device
     {
         self.program_running = true;
 
-        while self.program_running {
+        // a pause already passed, as when resuming a snapshot, stops at once
+        while self.program_running && self.pause_at.is_none_or(|pause_at| self.instructions_executed < pause_at) {
             let instruction_pointer: usize = self.instruction_pointer;
             let instruction: &String = &self.program_lines[instruction_pointer].clone();
+            if self.history_limit > 0
+            {
+                self.record_undo(instruction);
+            }
             self.execute_instruction(instruction);
+            if self.awaiting_guess
+            {
+                self.history.pop_back();
+                break;
+            }
             self.instructions_executed += 1;
+
+            if self.record_profile
+            {
+                self.record_step(instruction_pointer);
+            }
+        }
+    }
+
+    // carries on with a paused or restored run
+    pub fn resume(&mut self) -> Outcome
+    {
+        if self.program_running
+        {
+            self.device_state = DeviceState::ExecutionPhase;
+            self.run_program_lines();
+            self.device_state = DeviceState::IdlePhase;
+        }
+
+        return self.outcome();
+    }
+
+    fn record_step(&mut self, line: usize)
+    {
+        if self.profile.line_executions.len() < self.program_lines.len()
+        {
+            self.profile.line_executions.resize(self.program_lines.len(), 0);
+        }
+
+        self.profile.line_executions[line] += 1;
+
+        if self.program_running
+        {
+            *self.profile.edge_executions.entry((line, self.instruction_pointer)).or_insert(0) += 1;
         }
     }
 
     #[allow(dead_code)]
-    pub fn execute_program(&mut self, start_point: Option<usize>)
+    pub fn execute_program(&mut self, start_point: Option<usize>) -> Outcome
     {
         if !self.has_loaded_input
         {
-            eprintln!("WARNING: no input loaded");
+            self.warn("no input loaded");
         }
 
         self.device_state = DeviceState::ExecutionPhase;
@@ -566,6 +1107,19 @@ impl Device
         self.run_program_lines();
 
         self.device_state = DeviceState::IdlePhase;
+
+        return self.outcome();
+    }
+
+    pub fn outcome(&self) -> Outcome
+    {
+        match (&self.fault, self.verdict)
+        {
+            (Some(fault), _) => Outcome::Fault(fault.clone()),
+            (None, Some(true)) => Outcome::Accepted,
+            (None, Some(false)) => Outcome::Rejected,
+            (None, None) => Outcome::Halted,
+        }
     }
 
     #[allow(dead_code)]
@@ -584,19 +1138,50 @@ impl Device
     pub fn load_input_variable(&mut self, input_variable: &str, input_value: u32)
     {
         self.device_state = DeviceState::InputPhase;
+        let input_bits: BitVec = self.arithmetic.to_bits(input_value as i64);
         let input_index: usize = input_variable.trim_start_matches("i").parse::<usize>().unwrap();
         if input_index == self.input_variables.len()
         {
-            let new_variable = Variable::new(input_value);
+            let new_variable = Variable::from_bits(input_bits);
             self.input_variables.push(new_variable);
         } else
         {
-            self.input_variables[input_index].set_value(input_value);
+            self.input_variables[input_index].set_bits(input_bits);
         }
         self.has_loaded_input = true;
         self.device_state = DeviceState::IdlePhase;
     }
 
+    #[allow(dead_code)]
+    pub fn load_input(&mut self, input_variable: &str, input: &InputValue)
+    {
+        match input
+        {
+            InputValue::Number(number) => self.load_input_variable(input_variable, *number),
+            InputValue::Bits(bits) =>
+            {
+                self.load_input_variable(input_variable, 0);
+
+                let input_index: usize = input_variable.trim_start_matches("i").parse::<usize>().unwrap();
+                self.input_variables[input_index] = Variable
+                {
+                    value:      bits.clone(),
+                    max_size:   bits.len(),
+                    exact:      true,
+                };
+            }
+        }
+    }
+
+    // loads the inputs as i0, i1, and so on
+    pub fn load_inputs(&mut self, inputs: &[InputValue])
+    {
+        for (index, input) in inputs.iter().enumerate()
+        {
+            self.load_input(&format!("i{index}"), input);
+        }
+    }
+
     #[allow(dead_code)]
     pub fn clear_device_execution_memory(&mut self)
     {
@@ -604,6 +1189,17 @@ impl Device
         self.write_bits = Vec::<Variable>::new();
         self.flags = [false; NUM_FLAGS];
         self.instructions_executed = 0;
+        self.profile = Profile::default();
+        self.fault = None;
+        self.verdict = None;
+        self.heads = Vec::new();
+        self.output = BitVec::new();
+        self.captured_bit = None;
+        self.output_written = 0;
+        self.guesses_used = 0;
+        self.awaiting_guess = false;
+        self.rng = Rng::new(self.seed);
+        self.history = VecDeque::new();
     }
 
     #[allow(dead_code)]
@@ -612,81 +1208,38 @@ impl Device
         self.input_variables = Vec::<Variable>::new();
     }
 
+    // (input bits, flag bits, register and head position bits); see
+    // space_usage for the same with names
     #[allow(dead_code)]
     pub fn count_touched_memory(&mut self) -> (usize, usize, usize)
     {
-        let input_space_used: &usize = &self.input_variables.clone()
-            .iter()
-            .fold(0 as usize, |acc, var| acc + var.max_size);
-        let num_touched_flags: usize = self.flags.len();
-        let num_touched_bits: &usize = &self.write_bits.len();
-        let num_touched_variables: &usize = &self.write_variables.clone()
-            .iter()
-            .fold(0 as usize, |acc, var| acc + var.max_size);
-        
-        return (*input_space_used, num_touched_flags, *num_touched_bits +  *num_touched_variables);
-    }
+        let space: SpaceUsage = self.space_usage();
 
-    fn pretty_print_header(memory_type: &str)
-    {
-        eprintln!("{}S", memory_type);
-        eprintln!("{:->36}","");
-        eprintln!("{: ^10}|{: ^13}|{: ^10}", memory_type, "VALUE", "MAX SIZE");
-        eprintln!("{:->11}{:->14}{:->11}", "+", "+", "");
+        return (space.input, space.flags, space.registers + space.head_positions);
     }
 
-    #[allow(dead_code)]
-    pub fn pretty_print_variables(&mut self)
+    // a two-way head has to remember where it is; a streaming head does not
+    pub fn head_position_bits(&self) -> usize
     {
-        Self::pretty_print_header(" VARIABLE");
-        for i in 0..self.write_variables.len()
+        if self.input_access != InputAccess::TwoWay
         {
-            let variable = self.write_variables[i].clone();
-            eprintln!("v{:02}{: <7}| {: <11} | {: <8}", i, "", Variable::bits_to_u32(variable.value), variable.max_size);
+            return 0;
         }
-        eprintln!();
-    }
 
-    #[allow(dead_code)]
-    pub fn pretty_print_bit_variables(&mut self)
-    {
-        Self::pretty_print_header(" BIT");
-        for i in 0..self.write_bits.len()
-        {
-            let variable = self.write_bits[i].clone();
-            eprintln!("b{:02}{: <7}| {: <11} | {: <8}", i, "", Variable::bits_to_u32(variable.value), variable.max_size);
-        }
-        eprintln!();
+        return self.heads.iter()
+            .enumerate()
+            .filter(|(_, head)| head.used)
+            .map(|(index, _)| match &self.recomputed
+            {
+                Some(recomputed) if index == 0 => Head::position_bits(recomputed.length),
+                _other => Head::position_bits(self.input_variables[index].value.len()),
+            })
+            .sum();
     }
 
-    #[allow(dead_code)]
-    pub fn pretty_print_input_variables(&mut self)
+    pub fn pretty_print_memory(&self)
     {
-        Self::pretty_print_header(" INPUT");
-        for i in 0..self.input_variables.len()
-        {
-            let variable = self.input_variables[i].clone();
-            eprintln!("i{:02}{: <7}| {: <11} | {: <8}", i, "", Variable::bits_to_u32(variable.value), variable.max_size);
-        }
-        eprintln!();
-    }
-
-    #[allow(dead_code)]
-    pub fn pretty_print_memory(&mut self)
-    {
-        self.pretty_print_input_variables();
-        self.pretty_print_variables();
-        self.pretty_print_bit_variables();
-        eprintln!(" MEMORY USAGE");
-        eprintln!("{:->36}","");
-        eprintln!("{: ^11}|{}", "MEMORY", "BITS USED");
-        let (input_memory, flags, execution_memory) = self.count_touched_memory();
-        eprintln!("{:->12}{:->24}", "+", "");
-        eprintln!("{: ^11}| {}",  "INPUT", input_memory);
-        eprintln!("{: ^11}| {}",  "EXECUTION", flags + execution_memory);
-        eprintln!(" TIME USAGE");
-        eprintln!("{:->36}","");
-        eprintln!("{: ^11}| {}",  "EXECUTIONS", self.instructions_executed);
+        eprint!("{}", self.report());
     }
 
 }
@@ -0,0 +1,340 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+};
+
+use crate::instruction::{
+    Instruction,
+    Operand,
+    CONDITIONS,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity
+{
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic
+{
+    pub line:       usize,
+    pub severity:   Severity,
+    pub message:    String,
+}
+
+impl fmt::Display for Diagnostic
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let severity: &str = match self.severity
+        {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+
+        // `line` counts instructions from 0, as `jmp` does, not lines of text
+        write!(f, "instruction {}: {}: {}", self.line, severity, self.message)
+    }
+}
+
+type RegisterSet = BTreeSet<Operand>;
+
+fn holds_bit(operand: &str, known_bits: &RegisterSet) -> bool
+{
+    match Operand::parse(operand)
+    {
+        Some(Operand::Bit(_)) => true,
+        Some(Operand::Immediate(value)) => (0..=1).contains(&value),
+        Some(register) => known_bits.contains(&register),
+        None => false,
+    }
+}
+
+fn writes_bit(instruction: &Instruction, known_bits: &RegisterSet) -> bool
+{
+    match instruction.operator.as_str()
+    {
+        "stnb" | "badd" | "badc" | "bsub" | "bsbu" => true,
+        "stf" | "read" | "guess" | "rand" => true,
+        "set" => holds_bit(instruction.operand(1), known_bits),
+        "bsr" | "not" => holds_bit(instruction.operand(0), known_bits),
+        "and" => holds_bit(instruction.operand(0), known_bits) || holds_bit(instruction.operand(1), known_bits),
+        "or" | "xor" => holds_bit(instruction.operand(0), known_bits) && holds_bit(instruction.operand(1), known_bits),
+        _other => false,
+    }
+}
+
+pub fn lint_program(program: &[Instruction]) -> Vec<Diagnostic>
+{
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut report = |line: usize, severity: Severity, message: String|
+    {
+        diagnostics.push(Diagnostic { line, severity, message });
+    };
+
+    // DECODING
+    for (line, instruction) in program.iter().enumerate()
+    {
+        let info = match instruction.info()
+        {
+            Some(info) => info,
+            None =>
+            {
+                report(line, Severity::Error, format!("unknown operator `{}`", instruction.operator));
+                continue;
+            }
+        };
+
+        if instruction.operands.len() != info.num_operands && !(instruction.operator == "jon" && instruction.operands.is_empty())
+        {
+            report(line, Severity::Error, format!("`{}` takes {} operands, found {}", info.operator, info.num_operands, instruction.operands.len()));
+        }
+
+        if let Some(index) = instruction.condition_operand()
+        {
+            if !CONDITIONS.contains(&instruction.operand(index))
+            {
+                report(line, Severity::Error, format!("unknown condition `{}`", instruction.operand(index)));
+            }
+        }
+        if instruction.operator == "jon"
+        {
+            continue;
+        }
+
+        for (index, operand) in instruction.operands.iter().enumerate()
+        {
+            if instruction.condition_operand() != Some(index) && Operand::parse(operand).is_none()
+            {
+                report(line, Severity::Error, format!("invalid operand `{}`", operand));
+            }
+        }
+
+        for destination in instruction.written_operands()
+        {
+            match destination
+            {
+                Operand::Input(_) | Operand::InputAt(_) => report(line, Severity::Error, format!("writes input {} during execution", destination)),
+                Operand::Immediate(_) => report(line, Severity::Error, format!("writes to immediate {}", destination)),
+                _other => {}
+            }
+        }
+
+        if matches!(instruction.operator.as_str(), "idiv" | "imod") && Operand::parse(instruction.operand(1)) == Some(Operand::Immediate(0))
+        {
+            report(line, Severity::Error, "divides by zero".to_string());
+        }
+
+        if instruction.operator == "jmp"
+        {
+            match instruction.jump_target()
+            {
+                Some(target) if target >= program.len() =>
+                {
+                    report(line, Severity::Error, format!("jumps to instruction {} past the end of the program", target));
+                }
+                Some(_) => {}
+                None =>
+                {
+                    report(line, Severity::Warning, format!("jump target `{}` is not a line number", instruction.operand(0)));
+                }
+            }
+        }
+    }
+
+    // REACHABILITY
+    let mut reachable: Vec<bool> = vec![false; program.len()];
+    let mut pending: Vec<usize> = if program.is_empty() { Vec::new() } else { vec![0] };
+    let mut runs_off_end: BTreeSet<usize> = BTreeSet::new();
+
+    while let Some(line) = pending.pop()
+    {
+        if reachable[line]
+        {
+            continue;
+        }
+        reachable[line] = true;
+
+        for successor in program[line].successors(line)
+        {
+            if successor >= program.len()
+            {
+                if program[line].operator != "jmp"
+                {
+                    runs_off_end.insert(line);
+                }
+            }
+            else if !reachable[successor]
+            {
+                pending.push(successor);
+            }
+        }
+    }
+
+    for (line, is_reachable) in reachable.iter().enumerate()
+    {
+        // padding is never worth reporting, nor is a closing `end` that only
+        // follows an instruction that never falls through, such as the one
+        // the assembler appends after `acc`
+        let closing_end: bool = line + 1 == program.len() && program[line].operator == "end"
+            && line > 0 && !program[line - 1].successors(line - 1).contains(&line);
+        if !is_reachable && program[line].operator != "non" && !closing_end
+        {
+            report(line, Severity::Warning, "unreachable".to_string());
+        }
+    }
+
+    for line in runs_off_end
+    {
+        report(line, Severity::Error, "execution runs off the end of the program without `end`, `acc` or `rej`".to_string());
+    }
+
+    // READS BEFORE WRITES
+    // must: written on every path to the line; may: written on some path;
+    // bits: holds a single bit on every path
+    let mut must_written: Vec<Option<RegisterSet>> = vec![None; program.len()];
+    let mut may_written: Vec<RegisterSet> = vec![RegisterSet::new(); program.len()];
+    let mut must_bits: Vec<RegisterSet> = vec![RegisterSet::new(); program.len()];
+
+    if !program.is_empty()
+    {
+        must_written[0] = Some(RegisterSet::new());
+    }
+
+    let mut changed: bool = true;
+    while changed
+    {
+        changed = false;
+
+        for line in 0..program.len()
+        {
+            let must_before: RegisterSet = match &must_written[line]
+            {
+                Some(registers) => registers.clone(),
+                None => continue,
+            };
+            let mut must_after: RegisterSet = must_before;
+            let mut may_after: RegisterSet = may_written[line].clone();
+            let mut bits_after: RegisterSet = must_bits[line].clone();
+            let writes_bit: bool = writes_bit(&program[line], &must_bits[line]);
+
+            for destination in program[line].written_operands()
+            {
+                must_after.insert(destination);
+                may_after.insert(destination);
+
+                if writes_bit
+                {
+                    bits_after.insert(destination);
+                }
+                else
+                {
+                    bits_after.remove(&destination);
+                }
+            }
+
+            for successor in program[line].successors(line)
+            {
+                if successor >= program.len()
+                {
+                    continue;
+                }
+
+                let (must_merged, bits_merged) = match &must_written[successor]
+                {
+                    Some(registers) =>
+                    (
+                        registers.intersection(&must_after).cloned().collect::<RegisterSet>(),
+                        must_bits[successor].intersection(&bits_after).cloned().collect::<RegisterSet>(),
+                    ),
+                    None => (must_after.clone(), bits_after.clone()),
+                };
+                let may_merged: RegisterSet = may_written[successor].union(&may_after).cloned().collect::<RegisterSet>();
+
+                if must_written[successor].as_ref() != Some(&must_merged) || may_written[successor] != may_merged || must_bits[successor] != bits_merged
+                {
+                    must_written[successor] = Some(must_merged);
+                    may_written[successor] = may_merged;
+                    must_bits[successor] = bits_merged;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    for (line, instruction) in program.iter().enumerate()
+    {
+        let must_before: &RegisterSet = match &must_written[line]
+        {
+            Some(registers) => registers,
+            None => continue,
+        };
+
+        for source in instruction.read_operands()
+        {
+            if !matches!(source, Operand::Variable(_) | Operand::Bit(_)) || must_before.contains(&source)
+            {
+                continue;
+            }
+
+            if may_written[line].contains(&source)
+            {
+                report(line, Severity::Warning, format!("{} may be read before it is written", source));
+            }
+            else
+            {
+                report(line, Severity::Error, format!("{} is read before it is written", source));
+            }
+        }
+
+        if let Some(info) = instruction.info()
+        {
+            for index in info.bit_operands.iter()
+            {
+                match Operand::parse(instruction.operand(*index))
+                {
+                    Some(Operand::Immediate(value)) if !(0..=1).contains(&value) =>
+                    {
+                        report(line, Severity::Error, format!("`{}` operand {} is not a bit", info.operator, value));
+                    }
+                    Some(operand @ (Operand::Variable(_) | Operand::Input(_))) if !must_bits[line].contains(&operand) =>
+                    {
+                        report(line, Severity::Warning, format!("`{}` operand {} may not be a bit", info.operator, operand));
+                    }
+                    _other => {}
+                }
+            }
+        }
+
+        // variables are created in order, so writing vN needs vN-1 to exist
+        for destination in instruction.written_operands()
+        {
+            let previous: Operand = match destination
+            {
+                Operand::Variable(index) if index > 0 => Operand::Variable(index - 1),
+                Operand::Bit(index) if index > 0 => Operand::Bit(index - 1),
+                _other => continue,
+            };
+
+            if must_before.contains(&previous) || must_before.contains(&destination)
+            {
+                continue;
+            }
+
+            if may_written[line].contains(&previous)
+            {
+                report(line, Severity::Warning, format!("{} may be created before {}", destination, previous));
+            }
+            else
+            {
+                report(line, Severity::Error, format!("{} is created before {}", destination, previous));
+            }
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+
+    return diagnostics;
+}
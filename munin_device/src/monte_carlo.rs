@@ -0,0 +1,131 @@
+use std::fmt;
+
+use crate::{
+    outcome::Outcome,
+    random::Rng,
+    Device,
+};
+
+// the smallest, mean and largest of a set of measurements
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Distribution
+{
+    pub min:    u64,
+    pub mean:   f64,
+    pub max:    u64,
+}
+
+impl Distribution
+{
+    pub fn of(values: &[u64]) -> Self
+    {
+        return Self
+        {
+            min:    values.iter().copied().min().unwrap_or(0),
+            mean:   values.iter().sum::<u64>() as f64 / values.len().max(1) as f64,
+            max:    values.iter().copied().max().unwrap_or(0),
+        };
+    }
+}
+
+impl fmt::Display for Distribution
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{} / {:.2} / {}", self.min, self.mean, self.max)
+    }
+}
+
+pub struct MonteCarloReport
+{
+    pub seed:       u64,
+    pub runs:       u32,
+    pub accepted:   u32,
+    pub rejected:   u32,
+    // halted or faulted
+    pub other:      u32,
+    pub space:      Distribution,
+    pub time:       Distribution,
+}
+
+impl MonteCarloReport
+{
+    pub fn probability(&self) -> f64
+    {
+        return self.accepted as f64 / self.runs.max(1) as f64;
+    }
+
+    // the 95% Wilson score interval for the acceptance probability, which
+    // stays inside [0, 1] even when every run agrees
+    pub fn confidence_interval(&self) -> (f64, f64)
+    {
+        const Z: f64 = 1.96;
+
+        let n: f64 = self.runs.max(1) as f64;
+        let p: f64 = self.probability();
+        let centre: f64 = (p + Z * Z / (2.0 * n)) / (1.0 + Z * Z / n);
+        let margin: f64 = Z / (1.0 + Z * Z / n) * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt();
+
+        return ((centre - margin).max(0.0), (centre + margin).min(1.0));
+    }
+}
+
+impl fmt::Display for MonteCarloReport
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let (low, high) = self.confidence_interval();
+
+        writeln!(f, " MONTE CARLO (seed {})", self.seed)?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}| {}", "RUNS", self.runs)?;
+        writeln!(f, "{: ^11}| {}", "ACCEPTED", self.accepted)?;
+        writeln!(f, "{: ^11}| {}", "REJECTED", self.rejected)?;
+        writeln!(f, "{: ^11}| {}", "OTHER", self.other)?;
+        writeln!(f, "{: ^11}| {:.4}", "P(ACCEPT)", self.probability())?;
+        writeln!(f, "{: ^11}| [{:.4}, {:.4}]", "95% CI", low, high)?;
+        writeln!(f, " MIN / MEAN / MAX")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}| {}", "EXECUTION", self.space)?;
+        write!(f, "{: ^11}| {}", "EXECUTIONS", self.time)
+    }
+}
+
+// runs a program `runs` times, each with its own seed drawn from `seed`, so
+// the whole experiment and every run in it can be repeated
+pub fn monte_carlo(device: &mut Device, runs: u32, seed: u64) -> MonteCarloReport
+{
+    let mut seeds: Rng = Rng::new(seed);
+    let mut report: MonteCarloReport = MonteCarloReport
+    {
+        seed,
+        runs,
+        accepted:   0,
+        rejected:   0,
+        other:      0,
+        space:      Distribution::of(&[]),
+        time:       Distribution::of(&[]),
+    };
+    let mut spaces: Vec<u64> = Vec::new();
+    let mut times: Vec<u64> = Vec::new();
+
+    for _ in 0..runs
+    {
+        device.seed = seeds.next_u64();
+
+        match device.execute_program(None)
+        {
+            Outcome::Accepted => report.accepted += 1,
+            Outcome::Rejected => report.rejected += 1,
+            _other => report.other += 1,
+        }
+
+        spaces.push(device.space_usage().execution() as u64);
+        times.push(device.instructions_executed as u64);
+    }
+
+    report.space = Distribution::of(&spaces);
+    report.time = Distribution::of(&times);
+
+    return report;
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+use crate::fault::Fault;
+
+// how a run ended: `acc` and `rej` give an answer, `end` only stops
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome
+{
+    Accepted,
+    Rejected,
+    Halted,
+    Fault(Fault),
+}
+
+impl Outcome
+{
+    pub fn is_accepted(&self) -> bool
+    {
+        return *self == Outcome::Accepted;
+    }
+
+    // a short name for reports and CSV columns
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Outcome::Accepted => "accepted",
+            Outcome::Rejected => "rejected",
+            Outcome::Halted => "halted",
+            Outcome::Fault(_) => "fault",
+        }
+    }
+}
+
+impl fmt::Display for Outcome
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Outcome::Fault(fault) => write!(f, "fault at {}", fault),
+            _other => write!(f, "{}", self.name()),
+        }
+    }
+}
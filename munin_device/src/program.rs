@@ -0,0 +1,108 @@
+use std::{
+    fs,
+    path::Path,
+};
+
+use crate::{
+    bytecode::Bytecode,
+    instruction::{
+        decode_program,
+        Instruction,
+    },
+};
+
+// a Munin assembly program, one instruction per line, without comments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program
+{
+    pub lines:  Vec<String>,
+}
+
+// the lines of Munin assembly that hold instructions, with their 1-based
+// line numbers; every loader reads a program through this
+pub(crate) fn instruction_lines(text: &str) -> impl Iterator<Item = (usize, &str)>
+{
+    return text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with(";") && !line.trim().is_empty())
+        .map(|(line_index, line)| (line_index + 1, line));
+}
+
+impl Program
+{
+    // reads Munin assembly, checking every operator and operand count; blank
+    // lines and `;` comments are dropped, and errors give the line in text
+    pub fn parse(text: &str) -> Result<Self, String>
+    {
+        let mut lines: Vec<String> = Vec::new();
+
+        for (line_number, line) in instruction_lines(text)
+        {
+            let instruction: Instruction = Instruction::parse(line);
+            let info = instruction.info()
+                .ok_or(format!("line {}: unknown operator `{}`", line_number, instruction.operator))?;
+
+            // `jon` may leave out its condition
+            let optional: usize = (instruction.operator == "jon") as usize;
+            if instruction.operands.len() + optional < info.num_operands || instruction.operands.len() > info.num_operands
+            {
+                return Err(format!("line {}: `{}` takes {} operands", line_number, instruction.operator, info.num_operands));
+            }
+
+            lines.push(line.to_string());
+        }
+
+        return Ok(Self { lines });
+    }
+
+    // reads a file of Munin assembly or bytecode
+    pub fn load(file_path: impl AsRef<Path>) -> Result<Self, String>
+    {
+        let bytes: Vec<u8> = fs::read(file_path).map_err(|error| error.to_string())?;
+
+        if Bytecode::is_bytecode(&bytes)
+        {
+            return Ok(Self::from_bytecode(&Bytecode::decode(&bytes)?));
+        }
+
+        return Self::parse(&String::from_utf8(bytes).map_err(|_| "program is not UTF-8".to_string())?);
+    }
+
+    pub fn from_bytecode(bytecode: &Bytecode) -> Self
+    {
+        return Self
+        {
+            lines:  bytecode.program.iter().map(|instruction| instruction.to_string()).collect::<Vec<String>>(),
+        };
+    }
+
+    pub fn instructions(&self) -> Vec<Instruction>
+    {
+        return decode_program(&self.lines);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::fs;
+
+    use super::Program;
+    use crate::Device;
+
+    #[test]
+    fn loaders_agree_on_blank_lines()
+    {
+        let text: &str = "; adds one\nset v0 1\n\n   \niadd v0 1\n; done\n\nend\n";
+        let file_path = std::env::temp_dir().join(format!("munin-loaders-{}.asm", std::process::id()));
+        fs::write(&file_path, text).unwrap();
+
+        let mut device: Device = Device::new();
+        device.load_program(&file_path);
+        let program: Program = Program::load(&file_path).unwrap();
+        fs::remove_file(&file_path).unwrap();
+
+        assert_eq!(device.program_lines, program.lines);
+        assert_eq!(program.lines, vec!["set v0 1", "iadd v0 1", "end"]);
+    }
+}
@@ -0,0 +1,37 @@
+// splitmix64: small, fast and good enough for coin flips; the same seed
+// always gives the same bits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng
+{
+    state:  u64,
+}
+
+impl Rng
+{
+    pub fn new(seed: u64) -> Self
+    {
+        return Self { state: seed };
+    }
+
+    // Rng::new(rng.state()) carries on where rng is
+    pub fn state(&self) -> u64
+    {
+        return self.state;
+    }
+
+    pub fn next_u64(&mut self) -> u64
+    {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z: u64 = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+        return z ^ (z >> 31);
+    }
+
+    pub fn next_bit(&mut self) -> bool
+    {
+        return self.next_u64() >> 63 == 1;
+    }
+}
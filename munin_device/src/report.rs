@@ -0,0 +1,274 @@
+use std::fmt;
+
+use bit_vec::BitVec;
+
+use crate::{
+    access::{
+        Head,
+        InputAccess,
+    },
+    arithmetic::ArithmeticMode,
+    input::InputValue,
+    outcome::Outcome,
+    program::Program,
+    Device,
+    Variable,
+};
+
+// how a run is set up; every field starts at the device's default
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunConfig
+{
+    pub arithmetic:     ArithmeticMode,
+    pub input_access:   InputAccess,
+    pub seed:           u64,
+    pub guesses:        Vec<bool>,
+    // pauses the run after this many instructions
+    pub max_steps:      Option<u32>,
+    pub record_profile: bool,
+}
+
+impl RunConfig
+{
+    pub fn new() -> Self
+    {
+        return Self::default();
+    }
+
+    pub fn arithmetic(mut self, arithmetic: ArithmeticMode) -> Self
+    {
+        self.arithmetic = arithmetic;
+        return self;
+    }
+
+    pub fn input_access(mut self, input_access: InputAccess) -> Self
+    {
+        self.input_access = input_access;
+        return self;
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self
+    {
+        self.seed = seed;
+        return self;
+    }
+
+    pub fn guesses(mut self, guesses: Vec<bool>) -> Self
+    {
+        self.guesses = guesses;
+        return self;
+    }
+
+    pub fn max_steps(mut self, max_steps: u32) -> Self
+    {
+        self.max_steps = Some(max_steps);
+        return self;
+    }
+
+    pub fn record_profile(mut self, record_profile: bool) -> Self
+    {
+        self.record_profile = record_profile;
+        return self;
+    }
+
+    pub fn apply(&self, device: &mut Device)
+    {
+        device.arithmetic = self.arithmetic;
+        device.input_access = self.input_access;
+        device.seed = self.seed;
+        device.guesses = self.guesses.clone();
+        device.pause_at = self.max_steps;
+        device.record_profile = self.record_profile;
+    }
+}
+
+// the bits a run touched; execution space is the flags, registers and head
+// positions, and the output tape is not part of it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpaceUsage
+{
+    // the largest size of every input
+    pub input:          usize,
+    pub flags:          usize,
+    // one per bit, and the largest size of every variable
+    pub registers:      usize,
+    // the positions of two-way heads
+    pub head_positions: usize,
+    pub output:         usize,
+}
+
+impl SpaceUsage
+{
+    pub fn execution(&self) -> usize
+    {
+        return self.flags + self.registers + self.head_positions;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterReport
+{
+    pub name:       String,
+    // as the device shows it: signed in signed mode, 0b... for bitstrings
+    pub value:      String,
+    pub bits:       BitVec,
+    pub max_size:   usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunReport
+{
+    pub outcome:        Outcome,
+    // stopped by max_steps or pause_at before finishing
+    pub paused:         bool,
+    pub space:          SpaceUsage,
+    pub instructions_executed: u32,
+    pub inputs:         Vec<RegisterReport>,
+    pub variables:      Vec<RegisterReport>,
+    pub bits:           Vec<RegisterReport>,
+    pub input_access:   InputAccess,
+    // every head used, by input
+    pub heads:          Vec<(usize, Head)>,
+    pub output:         BitVec,
+    // warnings the device raised, which Device::run does not print
+    pub warnings:       Vec<String>,
+}
+
+fn bits_to_text(bits: impl Iterator<Item = bool>) -> String
+{
+    return bits.map(|bit| if bit { '1' } else { '0' }).collect::<String>();
+}
+
+fn register_reports(prefix: &str, registers: &[Variable], value: impl Fn(&Variable) -> String) -> Vec<RegisterReport>
+{
+    return registers.iter()
+        .enumerate()
+        .map(|(index, register)| RegisterReport
+        {
+            name:       format!("{}{:02}", prefix, index),
+            value:      value(register),
+            bits:       register.value.clone(),
+            max_size:   register.max_size,
+        })
+        .collect::<Vec<RegisterReport>>();
+}
+
+fn input_value(arithmetic: &ArithmeticMode, register: &Variable) -> String
+{
+    match register.exact
+    {
+        true => format!("0b{}", bits_to_text(register.value.iter().rev())),
+        false => arithmetic.from_bits(&register.value).to_string(),
+    }
+}
+
+impl Device
+{
+    pub fn space_usage(&self) -> SpaceUsage
+    {
+        return SpaceUsage
+        {
+            input:          self.input_variables.iter().map(|register| register.max_size).sum(),
+            flags:          self.flags.len(),
+            registers:      self.write_bits.len() + self.write_variables.iter().map(|register| register.max_size).sum::<usize>(),
+            head_positions: self.head_position_bits(),
+            output:         self.output.len(),
+        };
+    }
+
+    pub fn report(&self) -> RunReport
+    {
+        return RunReport
+        {
+            outcome:        self.outcome(),
+            paused:         self.program_running,
+            space:          self.space_usage(),
+            instructions_executed: self.instructions_executed,
+            inputs:         register_reports("i", &self.input_variables, |register| input_value(&self.arithmetic, register)),
+            variables:      register_reports("v", &self.write_variables, |register| self.arithmetic.from_bits(&register.value).to_string()),
+            bits:           register_reports("b", &self.write_bits, |register| Variable::bits_to_u32(register.value.clone()).to_string()),
+            input_access:   self.input_access,
+            heads:          self.heads.iter().cloned().enumerate().filter(|(_, head)| head.used).collect::<Vec<(usize, Head)>>(),
+            output:         self.output.clone(),
+            warnings:       self.warnings.clone(),
+        };
+    }
+
+    // runs a program on a fresh device
+    pub fn run(program: &Program, inputs: &[InputValue], config: &RunConfig) -> RunReport
+    {
+        let mut device: Device = Device::new();
+        device.print_warnings = false;
+
+        config.apply(&mut device);
+        device.program_lines = program.lines.clone();
+        device.load_inputs(inputs);
+        device.execute_program(None);
+
+        return device.report();
+    }
+}
+
+fn write_registers(f: &mut fmt::Formatter, memory_type: &str, registers: &[RegisterReport]) -> fmt::Result
+{
+    writeln!(f, "{}S", memory_type)?;
+    writeln!(f, "{:->36}","")?;
+    writeln!(f, "{: ^10}|{: ^13}|{: ^10}", memory_type, "VALUE", "MAX SIZE")?;
+    writeln!(f, "{:->11}{:->14}{:->11}", "+", "+", "")?;
+    for register in registers.iter()
+    {
+        writeln!(f, "{}{: <7}| {: <11} | {: <8}", register.name, "", register.value, register.max_size)?;
+    }
+    writeln!(f)
+}
+
+impl fmt::Display for RunReport
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write_registers(f, " INPUT", &self.inputs)?;
+        write_registers(f, " VARIABLE", &self.variables)?;
+        write_registers(f, " BIT", &self.bits)?;
+        writeln!(f, " MEMORY USAGE")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}|{}", "MEMORY", "BITS USED")?;
+        writeln!(f, "{:->12}{:->24}", "+", "")?;
+        writeln!(f, "{: ^11}| {}",  "INPUT", self.space.input)?;
+        writeln!(f, "{: ^11}| {}",  "EXECUTION", self.space.execution())?;
+        if !self.output.is_empty()
+        {
+            writeln!(f, "{: ^11}| {}",  "OUTPUT", self.output.len())?;
+            writeln!(f, " OUTPUT TAPE")?;
+            writeln!(f, "{:->36}","")?;
+            writeln!(f, " {}", bits_to_text(self.output.iter()))?;
+        }
+        if self.input_access != InputAccess::RandomAccess
+        {
+            writeln!(f, " INPUT HEADS ({})", self.input_access)?;
+            writeln!(f, "{:->36}","")?;
+            writeln!(f, "{: ^10}|{: ^13}|{: ^10}", " INPUT", "POSITION", "PASSES")?;
+            writeln!(f, "{:->11}{:->14}{:->11}", "+", "+", "")?;
+            for (index, head) in self.heads.iter()
+            {
+                writeln!(f, "i{:02}{: <7}| {: <11} | {: <8}", index, "", head.position, head.passes)?;
+            }
+            if self.input_access == InputAccess::TwoWay
+            {
+                writeln!(f, "{: ^11}| {}",  "POSITIONS", self.space.head_positions)?;
+            }
+        }
+        if let Outcome::Fault(fault) = &self.outcome
+        {
+            writeln!(f, " FAULT")?;
+            writeln!(f, "{:->36}","")?;
+            writeln!(f, " {}", fault)?;
+        }
+        writeln!(f, " RESULT")?;
+        writeln!(f, "{:->36}","")?;
+        // a paused run has no outcome yet
+        writeln!(f, "{: ^11}| {}",  "OUTCOME", if self.paused { "paused" } else { self.outcome.name() })?;
+        writeln!(f, " TIME USAGE")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}| {}",  "EXECUTIONS", self.instructions_executed)
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    collections::{
+        HashSet,
+        VecDeque,
+    },
+    fmt,
+    str::FromStr,
+};
+
+use bit_vec::BitVec;
+
+use crate::{
+    access::Head,
+    outcome::Outcome,
+    Device,
+    DeviceState,
+    Variable,
+    NUM_FLAGS,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchOrder
+{
+    #[default]
+    BreadthFirst,
+    DepthFirst,
+}
+
+impl FromStr for SearchOrder
+{
+    type Err = String;
+
+    fn from_str(order: &str) -> Result<Self, Self::Err>
+    {
+        match order
+        {
+            "bfs" => Ok(SearchOrder::BreadthFirst),
+            "dfs" => Ok(SearchOrder::DepthFirst),
+            _other => Err(format!("unknown search order `{}`; expected bfs or dfs", order)),
+        }
+    }
+}
+
+impl fmt::Display for SearchOrder
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            SearchOrder::BreadthFirst => write!(f, "bfs"),
+            SearchOrder::DepthFirst => write!(f, "dfs"),
+        }
+    }
+}
+
+// the execution memory of a device stopped at a `guess`
+#[derive(Debug, Clone)]
+pub struct Configuration
+{
+    pub instruction_pointer:    usize,
+    pub write_variables:        Vec<Variable>,
+    pub write_bits:             Vec<Variable>,
+    pub flags:                  [bool; NUM_FLAGS],
+    pub heads:                  Vec<Head>,
+    pub output:                 BitVec,
+    pub output_written:         usize,
+    pub instructions_executed:  u32,
+}
+
+// two configurations with the same key run the same from there on; maximum
+// sizes, counters and the write-only output are left out
+type ConfigurationKey = (usize, Vec<BitVec>, Vec<BitVec>, [bool; NUM_FLAGS], Vec<isize>);
+
+impl Configuration
+{
+    pub fn capture(device: &Device) -> Self
+    {
+        return Self
+        {
+            instruction_pointer:    device.instruction_pointer,
+            write_variables:        device.write_variables.clone(),
+            write_bits:             device.write_bits.clone(),
+            flags:                  device.flags,
+            heads:                  device.heads.clone(),
+            output:                 device.output.clone(),
+            output_written:         device.output_written,
+            instructions_executed:  device.instructions_executed,
+        };
+    }
+
+    pub fn restore(&self, device: &mut Device)
+    {
+        device.instruction_pointer = self.instruction_pointer;
+        device.write_variables = self.write_variables.clone();
+        device.write_bits = self.write_bits.clone();
+        device.flags = self.flags;
+        device.heads = self.heads.clone();
+        device.output = self.output.clone();
+        device.output_written = self.output_written;
+        device.instructions_executed = self.instructions_executed;
+        device.fault = None;
+        device.verdict = None;
+        device.awaiting_guess = false;
+    }
+
+    fn key(&self) -> ConfigurationKey
+    {
+        let values = |registers: &[Variable]| registers.iter()
+            .map(|register| register.value.clone())
+            .collect::<Vec<BitVec>>();
+
+        return (
+            self.instruction_pointer,
+            values(&self.write_variables),
+            values(&self.write_bits),
+            self.flags,
+            self.heads.iter().map(|head| head.position).collect::<Vec<isize>>(),
+        );
+    }
+}
+
+pub struct SearchReport
+{
+    pub order:          SearchOrder,
+    // accepted if any branch accepts, otherwise rejected
+    pub outcome:        Outcome,
+    // the guesses of the accepting branch found
+    pub certificate:    Option<Vec<bool>>,
+    pub configurations: usize,
+    pub branches:       usize,
+    // the most execution space any one branch used
+    pub max_space:      usize,
+    pub steps:          u64,
+}
+
+impl fmt::Display for SearchReport
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        writeln!(f, " SEARCH ({})", self.order)?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}| {}", "OUTCOME", self.outcome.name())?;
+        let certificate: String = match &self.certificate
+        {
+            Some(guesses) => guesses.iter().map(|guess| if *guess { '1' } else { '0' }).collect::<String>(),
+            None => "none".to_string(),
+        };
+        writeln!(f, "{: ^11}| {}", "GUESSES", certificate)?;
+        writeln!(f, " MEMORY USAGE")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}| {}", "BRANCH", self.max_space)?;
+        writeln!(f, " TIME USAGE")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}| {}", "CONFIGS", self.configurations)?;
+        writeln!(f, "{: ^11}| {}", "BRANCHES", self.branches)?;
+        write!(f, "{: ^11}| {}", "EXECUTIONS", self.steps)
+    }
+}
+
+struct Search
+{
+    pending:    VecDeque<(Configuration, Vec<bool>)>,
+    visited:    HashSet<ConfigurationKey>,
+    report:     SearchReport,
+}
+
+impl Search
+{
+    // looks at where a run stopped: a new configuration at a `guess` is
+    // queued, and a finished branch is counted; true if it accepted
+    fn settle(&mut self, device: &mut Device, guesses: Vec<bool>, steps_before: u32) -> bool
+    {
+        self.report.max_space = self.report.max_space.max(device.space_usage().execution());
+        self.report.steps += (device.instructions_executed - steps_before) as u64;
+
+        if device.awaiting_guess
+        {
+            let configuration: Configuration = Configuration::capture(device);
+            if self.visited.insert(configuration.key())
+            {
+                self.report.configurations += 1;
+                self.pending.push_back((configuration, guesses));
+            }
+            return false;
+        }
+
+        self.report.branches += 1;
+
+        if device.outcome().is_accepted()
+        {
+            self.report.outcome = Outcome::Accepted;
+            self.report.certificate = Some(guesses);
+            return true;
+        }
+
+        return false;
+    }
+}
+
+// explores every run of a program that uses `guess`; each configuration
+// stopped at a `guess` is expanded once, with both bits, and the search stops
+// at the first branch to accept
+pub fn search(device: &mut Device, order: SearchOrder) -> SearchReport
+{
+    let mut search: Search = Search
+    {
+        pending:    VecDeque::new(),
+        visited:    HashSet::new(),
+        report:     SearchReport
+        {
+            order,
+            outcome:        Outcome::Rejected,
+            certificate:    None,
+            configurations: 0,
+            branches:       0,
+            max_space:      0,
+            steps:          0,
+        },
+    };
+
+    device.pause_on_guess = true;
+    device.guesses = Vec::new();
+    device.execute_program(None);
+
+    let mut accepted: bool = search.settle(device, Vec::new(), 0);
+
+    while !accepted
+    {
+        let next: Option<(Configuration, Vec<bool>)> = match order
+        {
+            SearchOrder::BreadthFirst => search.pending.pop_front(),
+            SearchOrder::DepthFirst => search.pending.pop_back(),
+        };
+
+        let (configuration, guesses) = match next
+        {
+            Some(next) => next,
+            None => break,
+        };
+
+        for guess in [false, true]
+        {
+            let mut branch_guesses: Vec<bool> = guesses.clone();
+            branch_guesses.push(guess);
+
+            configuration.restore(device);
+            device.guesses = branch_guesses.clone();
+            device.guesses_used = guesses.len();
+            device.device_state = DeviceState::ExecutionPhase;
+            device.run_program_lines();
+            device.device_state = DeviceState::IdlePhase;
+
+            accepted = search.settle(device, branch_guesses, configuration.instructions_executed);
+            if accepted
+            {
+                break;
+            }
+        }
+    }
+
+    device.pause_on_guess = false;
+    device.guesses = search.report.certificate.clone().unwrap_or_default();
+
+    return search.report;
+}
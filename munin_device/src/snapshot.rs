@@ -0,0 +1,278 @@
+use std::{
+    fs,
+    path::Path,
+};
+
+use bit_vec::BitVec;
+
+use crate::{
+    access::{
+        Head,
+        InputAccess,
+    },
+    arithmetic::ArithmeticMode,
+    fault::Fault,
+    random::Rng,
+    Device,
+    DeviceState,
+    Variable,
+    NUM_FLAGS,
+};
+
+pub const SNAPSHOT_HEADER: &str = "munin-snapshot 1";
+
+// bit 0 first; `-` for no bits
+fn bits_to_text(bits: &BitVec) -> String
+{
+    if bits.is_empty()
+    {
+        return "-".to_string();
+    }
+
+    return bits.iter().map(|bit| if bit { '1' } else { '0' }).collect::<String>();
+}
+
+fn text_to_bits(text: &str) -> Result<BitVec, String>
+{
+    if text == "-"
+    {
+        return Ok(BitVec::new());
+    }
+
+    return text.chars()
+        .map(|bit| match bit
+        {
+            '0' => Ok(false),
+            '1' => Ok(true),
+            _other => Err(format!("`{}` is not a bit", bit)),
+        })
+        .collect::<Result<BitVec, String>>();
+}
+
+fn parse_number<T: std::str::FromStr>(text: Option<&str>, field: &str) -> Result<T, String>
+{
+    return text.and_then(|text| text.parse::<T>().ok())
+        .ok_or(format!("invalid {} in snapshot", field));
+}
+
+fn register_line(kind: &str, index: usize, register: &Variable) -> String
+{
+    return format!("{} {} {} {} {}", kind, index, register.max_size, register.exact as u8, bits_to_text(&register.value));
+}
+
+fn parse_register(fields: &[&str]) -> Result<Variable, String>
+{
+    return Ok(Variable
+    {
+        max_size:   parse_number::<usize>(fields.get(2).copied(), "register size")?,
+        exact:      parse_number::<u8>(fields.get(3).copied(), "register flag")? == 1,
+        value:      text_to_bits(fields.get(4).copied().unwrap_or("-"))?,
+    });
+}
+
+fn fault_line(fault: &Option<Fault>) -> String
+{
+    match fault
+    {
+        None => "fault none".to_string(),
+        Some(Fault::DivisionByZero { line }) => format!("fault division-by-zero {}", line),
+        Some(Fault::Underflow { line }) => format!("fault underflow {}", line),
+        Some(Fault::Overflow { line }) => format!("fault overflow {}", line),
+        Some(Fault::InputAccess { line, reason }) => format!("fault input-access {} {}", line, reason),
+        Some(Fault::HeadOffTape { line, input }) => format!("fault head-off-tape {} {}", line, input),
+        Some(Fault::IndexOutOfRange { line, operand, register }) => format!("fault index-out-of-range {} {} {}", line, operand, register),
+    }
+}
+
+fn parse_fault(fields: &[&str]) -> Result<Option<Fault>, String>
+{
+    let kind: &str = fields.get(1).copied().unwrap_or("");
+    if kind == "none"
+    {
+        return Ok(None);
+    }
+
+    let line: usize = parse_number(fields.get(2).copied(), "fault line")?;
+    let text = |index: usize| fields.get(index).map(|field| field.to_string())
+        .ok_or("invalid fault in snapshot".to_string());
+
+    return Ok(Some(match kind
+    {
+        "division-by-zero" => Fault::DivisionByZero { line },
+        "underflow" => Fault::Underflow { line },
+        "overflow" => Fault::Overflow { line },
+        "input-access" => Fault::InputAccess { line, reason: fields[3.min(fields.len())..].join(" ") },
+        "head-off-tape" => Fault::HeadOffTape { line, input: parse_number(fields.get(3).copied(), "fault input")? },
+        "index-out-of-range" => Fault::IndexOutOfRange { line, operand: text(3)?, register: text(4)? },
+        _other => return Err(format!("unknown fault `{}` in snapshot", kind)),
+    }));
+}
+
+// a snapshot is a line per field, then the program; profiles and
+// compositions are not saved
+impl Device
+{
+    pub fn to_snapshot(&self) -> String
+    {
+        let mut lines: Vec<String> = vec![SNAPSHOT_HEADER.to_string()];
+
+        let phase: &str = match self.device_state
+        {
+            DeviceState::IdlePhase => "idle",
+            DeviceState::InputPhase => "input",
+            DeviceState::ExecutionPhase => "execution",
+        };
+        let verdict: &str = match self.verdict
+        {
+            Some(true) => "accept",
+            Some(false) => "reject",
+            None => "none",
+        };
+
+        lines.push(format!("phase {}", phase));
+        lines.push(format!("running {}", self.program_running as u8));
+        lines.push(format!("ip {}", self.instruction_pointer));
+        lines.push(format!("executed {}", self.instructions_executed));
+        lines.push(format!("has-input {}", self.has_loaded_input as u8));
+        lines.push(format!("arithmetic {}", self.arithmetic));
+        lines.push(format!("input-access {}", self.input_access));
+        lines.push(format!("flags {}", self.flags.iter().map(|flag| if *flag { '1' } else { '0' }).collect::<String>()));
+        lines.push(format!("verdict {}", verdict));
+        lines.push(fault_line(&self.fault));
+        lines.push(format!("seed {}", self.seed));
+        lines.push(format!("rng {}", self.rng.state()));
+        lines.push(format!("guesses {} {}", self.guesses_used, bits_to_text(&self.guesses.iter().copied().collect::<BitVec>())));
+        lines.push(format!("output {} {}", self.output_written, bits_to_text(&self.output)));
+
+        for (index, register) in self.input_variables.iter().enumerate()
+        {
+            lines.push(register_line("input", index, register));
+        }
+        for (index, register) in self.write_variables.iter().enumerate()
+        {
+            lines.push(register_line("variable", index, register));
+        }
+        for (index, register) in self.write_bits.iter().enumerate()
+        {
+            lines.push(register_line("bit", index, register));
+        }
+        for (index, head) in self.heads.iter().enumerate()
+        {
+            lines.push(format!("head {} {} {} {}", index, head.position, head.used as u8, head.passes));
+        }
+
+        lines.push(format!("program {}", self.program_lines.len()));
+        lines.extend(self.program_lines.iter().cloned());
+
+        return lines.join("\n") + "\n";
+    }
+
+    pub fn from_snapshot(text: &str) -> Result<Self, String>
+    {
+        let mut lines = text.lines();
+
+        if lines.next() != Some(SNAPSHOT_HEADER)
+        {
+            return Err("not a Munin snapshot".to_string());
+        }
+
+        let mut device: Device = Device::new();
+
+        while let Some(line) = lines.next()
+        {
+            let fields: Vec<&str> = line.split_whitespace().collect::<Vec<&str>>();
+            let value: Option<&str> = fields.get(1).copied();
+
+            match fields.first().copied()
+            {
+                Some("phase") =>
+                {
+                    device.device_state = match value
+                    {
+                        Some("idle") => DeviceState::IdlePhase,
+                        Some("input") => DeviceState::InputPhase,
+                        Some("execution") => DeviceState::ExecutionPhase,
+                        _other => return Err("invalid phase in snapshot".to_string()),
+                    };
+                }
+                Some("running") => device.program_running = parse_number::<u8>(value, "running")? == 1,
+                Some("ip") => device.instruction_pointer = parse_number(value, "instruction pointer")?,
+                Some("executed") => device.instructions_executed = parse_number(value, "instruction count")?,
+                Some("has-input") => device.has_loaded_input = parse_number::<u8>(value, "has-input")? == 1,
+                Some("arithmetic") => device.arithmetic = value.unwrap_or("").parse::<ArithmeticMode>()?,
+                Some("input-access") => device.input_access = value.unwrap_or("").parse::<InputAccess>()?,
+                Some("flags") =>
+                {
+                    let flags: BitVec = text_to_bits(value.unwrap_or(""))?;
+                    if flags.len() != NUM_FLAGS
+                    {
+                        return Err("invalid flags in snapshot".to_string());
+                    }
+                    for (index, flag) in flags.iter().enumerate()
+                    {
+                        device.flags[index] = flag;
+                    }
+                }
+                Some("verdict") =>
+                {
+                    device.verdict = match value
+                    {
+                        Some("accept") => Some(true),
+                        Some("reject") => Some(false),
+                        _other => None,
+                    };
+                }
+                Some("fault") => device.fault = parse_fault(&fields)?,
+                Some("seed") => device.seed = parse_number(value, "seed")?,
+                Some("rng") => device.rng = Rng::new(parse_number(value, "generator state")?),
+                Some("guesses") =>
+                {
+                    device.guesses_used = parse_number(value, "guess count")?;
+                    device.guesses = text_to_bits(fields.get(2).copied().unwrap_or("-"))?.iter().collect::<Vec<bool>>();
+                }
+                Some("output") =>
+                {
+                    device.output_written = parse_number(value, "output length")?;
+                    device.output = text_to_bits(fields.get(2).copied().unwrap_or("-"))?;
+                }
+                Some("input") => device.input_variables.push(parse_register(&fields)?),
+                Some("variable") => device.write_variables.push(parse_register(&fields)?),
+                Some("bit") => device.write_bits.push(parse_register(&fields)?),
+                Some("head") =>
+                {
+                    device.heads.push(Head
+                    {
+                        position:   parse_number(fields.get(2).copied(), "head position")?,
+                        used:       parse_number::<u8>(fields.get(3).copied(), "head")? == 1,
+                        passes:     parse_number(fields.get(4).copied(), "head passes")?,
+                    });
+                }
+                Some("program") =>
+                {
+                    let length: usize = parse_number(value, "program length")?;
+                    device.program_lines = lines.by_ref().take(length).map(|line| line.to_string()).collect::<Vec<String>>();
+                    if device.program_lines.len() != length
+                    {
+                        return Err("snapshot ends inside the program".to_string());
+                    }
+                }
+                None => {}
+                Some(field) => return Err(format!("unknown snapshot field `{}`", field)),
+            }
+        }
+
+        return Ok(device);
+    }
+
+    pub fn save_snapshot(&self, file_path: impl AsRef<Path>)
+    {
+        fs::write(file_path, self.to_snapshot()).expect("unable to write");
+    }
+
+    pub fn load_snapshot(file_path: impl AsRef<Path>) -> Result<Self, String>
+    {
+        let text: String = fs::read_to_string(file_path).map_err(|error| error.to_string())?;
+
+        return Self::from_snapshot(&text);
+    }
+}
@@ -0,0 +1,412 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+    fs,
+    path::Path,
+};
+
+use crate::{
+    input::InputValue,
+    outcome::Outcome,
+};
+
+pub const BLANK: char = '_';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move
+{
+    Left,
+    Right,
+    Stay,
+}
+
+impl Move
+{
+    fn parse(text: &str) -> Option<Self>
+    {
+        match text
+        {
+            "L" => Some(Move::Left),
+            "R" => Some(Move::Right),
+            "S" => Some(Move::Stay),
+            _other => None,
+        }
+    }
+
+    fn offset(&self) -> isize
+    {
+        match self
+        {
+            Move::Left => -1,
+            Move::Right => 1,
+            Move::Stay => 0,
+        }
+    }
+}
+
+impl fmt::Display for Move
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Move::Left => write!(f, "L"),
+            Move::Right => write!(f, "R"),
+            Move::Stay => write!(f, "S"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition
+{
+    pub next_state: String,
+    // one symbol per work tape; the input tape is never written
+    pub writes:     Vec<char>,
+    // one move per tape, input tape first
+    pub moves:      Vec<Move>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tape
+{
+    pub cells:              HashMap<isize, char>,
+    pub head:               isize,
+    // leftmost and rightmost cells the head has been on
+    pub lowest_visited:     isize,
+    pub highest_visited:    isize,
+}
+
+impl Tape
+{
+    fn read(&self) -> char
+    {
+        return self.cells.get(&self.head).copied().unwrap_or(BLANK);
+    }
+
+    fn move_head(&mut self, head_move: Move)
+    {
+        self.head += head_move.offset();
+        self.lowest_visited = self.lowest_visited.min(self.head);
+        self.highest_visited = self.highest_visited.max(self.head);
+    }
+
+    pub fn cells_visited(&self) -> usize
+    {
+        return (self.highest_visited - self.lowest_visited + 1) as usize;
+    }
+}
+
+// a deterministic multi-tape machine with a read-only input tape (tape 0)
+// and work tapes 1 to k - 1; a missing transition rejects
+pub struct TuringMachine
+{
+    pub num_tapes:      usize,
+    pub alphabet:       Vec<char>,
+    pub states:         BTreeSet<String>,
+    pub start_state:    String,
+    pub accept_state:   String,
+    pub reject_state:   String,
+    pub transitions:    HashMap<(String, Vec<char>), Transition>,
+    pub tapes:          Vec<Tape>,
+    pub input_length:   usize,
+    pub state:          String,
+    pub steps_executed: u32,
+}
+
+impl Default for TuringMachine
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl TuringMachine
+{
+    pub fn new() -> Self
+    {
+        Self
+        {
+            num_tapes:      1,
+            alphabet:       vec!['0', '1'],
+            states:         BTreeSet::new(),
+            start_state:    String::new(),
+            accept_state:   String::new(),
+            reject_state:   String::new(),
+            transitions:    HashMap::new(),
+            tapes:          Vec::new(),
+            input_length:   0,
+            state:          String::new(),
+            steps_executed: 0,
+        }
+    }
+
+    pub fn load_program(&mut self, file_path: impl AsRef<Path>)
+    {
+        let text: String = fs::read_to_string(file_path).expect("Could not open file");
+
+        self.parse_program(&text);
+    }
+
+    // one directive or transition per line:
+    //     tapes K
+    //     alphabet 0 1 x
+    //     start q0
+    //     accept qa
+    //     reject qr
+    //     q0 0 _ -> q1 1 R R
+    // a transition names the state and the symbol under each head, then the
+    // next state, the symbol written on each work tape and a move (L, R or
+    // S) for each head; `;` starts a comment
+    pub fn parse_program(&mut self, text: &str)
+    {
+        *self = Self::new();
+
+        for (line_index, full_line) in text.lines().enumerate()
+        {
+            let line: &str = full_line.split(';').next().unwrap_or("").trim();
+            let tokens: Vec<&str> = line.split_whitespace().collect::<Vec<&str>>();
+            let line_number: usize = line_index + 1;
+
+            match tokens.first().copied()
+            {
+                None => {}
+                Some("tapes") =>
+                {
+                    self.num_tapes = tokens.get(1).and_then(|tapes| tapes.parse::<usize>().ok())
+                        .filter(|tapes| *tapes >= 1)
+                        .unwrap_or_else(|| panic!("Invalid tape count at line {}: {}", line_number, full_line));
+                }
+                Some("alphabet") =>
+                {
+                    self.alphabet = tokens[1..].iter()
+                        .map(|symbol| Self::parse_symbol(symbol, line_number))
+                        .collect::<Vec<char>>();
+                }
+                Some("start") => self.start_state = Self::parse_state(&tokens, line_number),
+                Some("accept") => self.accept_state = Self::parse_state(&tokens, line_number),
+                Some("reject") => self.reject_state = Self::parse_state(&tokens, line_number),
+                Some(_) => self.parse_transition(&tokens, line_number, full_line),
+            }
+        }
+
+        if self.start_state.is_empty() || self.accept_state.is_empty() || self.reject_state.is_empty()
+        {
+            panic!("Machine needs start, accept and reject states");
+        }
+
+        self.states.insert(self.start_state.clone());
+        self.states.insert(self.accept_state.clone());
+        self.states.insert(self.reject_state.clone());
+    }
+
+    fn parse_state(tokens: &[&str], line_number: usize) -> String
+    {
+        return tokens.get(1)
+            .unwrap_or_else(|| panic!("Missing state name at line {}", line_number))
+            .to_string();
+    }
+
+    fn parse_symbol(symbol: &str, line_number: usize) -> char
+    {
+        let mut characters = symbol.chars();
+
+        match (characters.next(), characters.next())
+        {
+            (Some(character), None) => character,
+            _other => panic!("Invalid symbol `{}` at line {}; symbols are single characters", symbol, line_number),
+        }
+    }
+
+    fn parse_transition(&mut self, tokens: &[&str], line_number: usize, line: &str)
+    {
+        let k: usize = self.num_tapes;
+
+        // state, k reads, ->, next state, k - 1 writes, k moves
+        if tokens.len() != 3 * k + 2 || tokens[k + 1] != "->"
+        {
+            panic!("Invalid transition at line {}: {}; expected {} tokens for {} tapes", line_number, line, 3 * k + 2, k);
+        }
+
+        let state: String = tokens[0].to_string();
+        let reads: Vec<char> = tokens[1..=k].iter()
+            .map(|symbol| self.checked_symbol(symbol, line_number))
+            .collect::<Vec<char>>();
+        let next_state: String = tokens[k + 2].to_string();
+        let writes: Vec<char> = tokens[k + 3..2 * k + 2].iter()
+            .map(|symbol| self.checked_symbol(symbol, line_number))
+            .collect::<Vec<char>>();
+        let moves: Vec<Move> = tokens[2 * k + 2..].iter()
+            .map(|head_move| Move::parse(head_move)
+                .unwrap_or_else(|| panic!("Invalid move `{}` at line {}; expected L, R or S", head_move, line_number)))
+            .collect::<Vec<Move>>();
+
+        self.states.insert(state.clone());
+        self.states.insert(next_state.clone());
+
+        let transition: Transition = Transition { next_state, writes, moves };
+        if self.transitions.insert((state.clone(), reads.clone()), transition).is_some()
+        {
+            panic!("Duplicate transition at line {}: {} already has a transition on {:?}", line_number, state, reads);
+        }
+    }
+
+    fn checked_symbol(&self, symbol: &str, line_number: usize) -> char
+    {
+        let symbol: char = Self::parse_symbol(symbol, line_number);
+
+        if symbol != BLANK && !self.alphabet.contains(&symbol)
+        {
+            panic!("Symbol `{}` at line {} is not in the alphabet", symbol, line_number);
+        }
+
+        return symbol;
+    }
+
+    // the input is written from bit 0 at cell 0; every head starts at cell 0
+    pub fn load_input(&mut self, input: &InputValue)
+    {
+        let bits: Vec<bool> = match input
+        {
+            InputValue::Number(number) => (0..input.len()).map(|i| (number >> i) & 1 == 1).collect::<Vec<bool>>(),
+            InputValue::Bits(bits) => bits.iter().collect::<Vec<bool>>(),
+        };
+
+        self.tapes = vec![Tape::default(); self.num_tapes];
+        self.input_length = bits.len();
+
+        for (cell, bit) in bits.into_iter().enumerate()
+        {
+            self.tapes[0].cells.insert(cell as isize, if bit { '1' } else { '0' });
+        }
+    }
+
+    pub fn is_halted(&self) -> bool
+    {
+        return self.state == self.accept_state || self.state == self.reject_state;
+    }
+
+    pub fn is_accepted(&self) -> bool
+    {
+        return self.state == self.accept_state;
+    }
+
+    // a machine stopped by the step limit has only halted
+    pub fn outcome(&self) -> Outcome
+    {
+        if self.is_accepted()
+        {
+            return Outcome::Accepted;
+        }
+        if self.is_halted()
+        {
+            return Outcome::Rejected;
+        }
+
+        return Outcome::Halted;
+    }
+
+    // runs until the machine halts or takes max_steps steps
+    pub fn execute_program(&mut self, max_steps: Option<u32>) -> Outcome
+    {
+        if self.tapes.len() != self.num_tapes
+        {
+            self.tapes = vec![Tape::default(); self.num_tapes];
+        }
+
+        self.state = self.start_state.clone();
+        self.steps_executed = 0;
+
+        while !self.is_halted() && max_steps.map_or(true, |max_steps| self.steps_executed < max_steps)
+        {
+            let reads: Vec<char> = self.tapes.iter().map(|tape| tape.read()).collect::<Vec<char>>();
+
+            let transition: Transition = match self.transitions.get(&(self.state.clone(), reads))
+            {
+                Some(transition) => transition.clone(),
+                None =>
+                {
+                    self.state = self.reject_state.clone();
+                    break;
+                }
+            };
+
+            for (tape, symbol) in self.tapes[1..].iter_mut().zip(transition.writes.iter())
+            {
+                tape.cells.insert(tape.head, *symbol);
+            }
+            for (tape, head_move) in self.tapes.iter_mut().zip(transition.moves.iter())
+            {
+                tape.move_head(*head_move);
+            }
+
+            self.state = transition.next_state;
+            self.steps_executed += 1;
+        }
+
+        return self.outcome();
+    }
+
+    // bits to store one work tape cell, blank included
+    pub fn bits_per_cell(&self) -> usize
+    {
+        return (self.alphabet.len() + 1).next_power_of_two().ilog2().max(1) as usize;
+    }
+
+    // bits to store the state, the machine's counterpart of the flags
+    pub fn state_bits(&self) -> usize
+    {
+        return self.states.len().next_power_of_two().ilog2().max(1) as usize;
+    }
+
+    pub fn work_cells(&self) -> Vec<usize>
+    {
+        return self.tapes.iter().skip(1).map(|tape| tape.cells_visited()).collect::<Vec<usize>>();
+    }
+
+    // (input bits, state bits, work tape bits), in the form of
+    // Device::count_touched_memory
+    pub fn count_touched_memory(&self) -> (usize, usize, usize)
+    {
+        let work_bits: usize = self.work_cells().iter().sum::<usize>() * self.bits_per_cell();
+
+        return (self.input_length, self.state_bits(), work_bits);
+    }
+
+    pub fn pretty_print_memory(&self)
+    {
+        eprint!("{}", self);
+    }
+}
+
+impl fmt::Display for TuringMachine
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let (input_memory, state_memory, work_memory) = self.count_touched_memory();
+
+        writeln!(f, " WORK TAPES")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^10}|{: ^13}|{: ^10}", " TAPE", "CELLS USED", "HEAD")?;
+        writeln!(f, "{:->11}{:->14}{:->11}", "+", "+", "")?;
+        for (index, tape) in self.tapes.iter().enumerate().skip(1)
+        {
+            writeln!(f, "t{:02}{: <7}| {: <11} | {: <8}", index, "", tape.cells_visited(), tape.head)?;
+        }
+        writeln!(f)?;
+        writeln!(f, " MEMORY USAGE")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}|{}", "MEMORY", "BITS USED")?;
+        writeln!(f, "{:->12}{:->24}", "+", "")?;
+        writeln!(f, "{: ^11}| {}",  "INPUT", input_memory)?;
+        writeln!(f, "{: ^11}| {}",  "EXECUTION", state_memory + work_memory)?;
+        writeln!(f, " RESULT")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}| {}",  "STATE", self.state)?;
+        writeln!(f, "{: ^11}| {}",  "OUTCOME", self.outcome().name())?;
+        writeln!(f, " TIME USAGE")?;
+        writeln!(f, "{:->36}","")?;
+        writeln!(f, "{: ^11}| {}",  "STEPS", self.steps_executed)
+    }
+}
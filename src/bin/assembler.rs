@@ -1,4 +1,13 @@
-use munin_assembler::Assembler;
+use std::fs;
+
+use munin_assembler::{
+    Assembler,
+    Disassembler,
+};
+use munin_device::{
+    Bytecode,
+    Program,
+};
 
 use clap::Parser;
 
@@ -11,7 +20,27 @@ struct Args {
 
     // Inputs
     #[arg(short, long)]
-    output: String
+    output: String,
+
+    /// Turn Munin assembly back into SBASIC
+    #[arg(short, long)]
+    disassemble: bool,
+
+    /// Remove padding and redundant instructions from the output
+    #[arg(short = 'O', long)]
+    optimize: bool,
+
+    /// Write binary bytecode instead of Munin assembly text
+    #[arg(short, long)]
+    bytecode: bool,
+
+    /// With --bytecode, encode a Munin assembly file instead of SBASIC
+    #[arg(short = 'a', long, requires = "bytecode")]
+    from_assembly: bool,
+
+    /// Turn a bytecode file back into Munin assembly
+    #[arg(short, long, conflicts_with_all = ["bytecode", "disassemble"])]
+    to_text: bool,
 }
 
 fn main() {
@@ -19,10 +48,53 @@ fn main() {
     let output_path = args.output;
     let file_path = args.file;
 
+    if args.disassemble
+    {
+        let mut disassembler = Disassembler::new();
+
+        disassembler.load_file(file_path);
+
+        disassembler.decompile_program(&output_path);
+
+        return;
+    }
+
+    if args.to_text
+    {
+        let bytes: Vec<u8> = fs::read(file_path).expect("Could not open file");
+        let bytecode: Bytecode = Bytecode::decode(&bytes)
+            .unwrap_or_else(|error| panic!("Could not load bytecode: {}", error));
+
+        let asm_lines: Vec<String> = bytecode.to_text();
+        fs::write(&output_path, asm_lines.join("\n") + "\n").expect("unable to write");
+
+        return;
+    }
+
+    if args.from_assembly
+    {
+        let asm_text: String = fs::read_to_string(file_path).expect("Could not open file");
+        let program: Program = Program::parse(&asm_text)
+            .unwrap_or_else(|error| panic!("Could not read program: {}", error));
+
+        let bytecode: Bytecode = Bytecode::from_program(&program.instructions());
+        fs::write(&output_path, bytecode.encode()).expect("unable to write");
+
+        return;
+    }
+
     let mut assembler = Assembler::new();
+    assembler.optimize = args.optimize;
 
     assembler.load_file(file_path);
 
-    assembler.compile_program(&output_path);
+    if args.bytecode
+    {
+        assembler.compile_bytecode(&output_path);
+    }
+    else
+    {
+        assembler.compile_program(&output_path);
+    }
 
 }
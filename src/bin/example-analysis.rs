@@ -1,19 +1,81 @@
 use clap::Parser;
 
 use munin_device::{
+    decode_program,
+    estimate_space,
     Device,
+    InputValue,
+    Outcome,
+    SpaceClass,
+    SpaceEstimate,
+    TuringMachine,
     Variable
 };
 
 const VALUES_FOR_PROFILING: [u32; 5] = [0x1, 0x2, 0x8, 0x80, 0x8000];
 const NUM_VALUES: usize = VALUES_FOR_PROFILING.len();
 
+// the class the measurements grow in, from the last three input lengths, each
+// double the one before: a log n term adds the same amount every time the
+// length doubles, and an n term adds more each time
+fn measured_class(execution_memory_usages: &[usize]) -> SpaceClass
+{
+    let count: usize = execution_memory_usages.len();
+    if count < 3
+    {
+        return SpaceClass::Constant;
+    }
+
+    let first_growth: usize = execution_memory_usages[count - 2].saturating_sub(execution_memory_usages[count - 3]);
+    let last_growth: usize = execution_memory_usages[count - 1].saturating_sub(execution_memory_usages[count - 2]);
+
+    match (first_growth, last_growth)
+    {
+        (0, 0) => SpaceClass::Constant,
+        (first, last) if last > first => SpaceClass::Linear,
+        _other => SpaceClass::Logarithmic,
+    }
+}
+
+fn print_complexity_analysis(input_lengths: &[usize], execution_memory_usages: &[usize], estimate: &SpaceEstimate)
+{
+    eprintln!("\n COMPLEXITY ANALYSIS");
+    eprintln!("{:->50}","");
+    eprintln!(" PREDICTED: {} = {}", estimate.class(), estimate.total);
+    eprintln!("{:->50}","");
+    eprintln!("{: ^14}|{: ^17}|{: ^17}", "INPUT LENGTH", "MEMORY USED", "PREDICTED");
+    for i in 0..input_lengths.len()
+    {
+        // measured space above the static bound means the analysis is wrong
+        let predicted: String = match estimate.total.evaluate(input_lengths[i])
+        {
+            Some(predicted) if execution_memory_usages[i] > predicted => format!("{} !", predicted),
+            Some(predicted) => predicted.to_string(),
+            None => "unbounded".to_string(),
+        };
+        eprintln!("{:->15}{:->18}{:->17}", "+", "+", "");
+        eprintln!(" {: <13}| {: <16}| {: <16}",  input_lengths[i], execution_memory_usages[i], predicted);
+    }
+    if input_lengths.iter().zip(execution_memory_usages.iter())
+        .any(|(length, used)| estimate.total.evaluate(*length).is_some_and(|predicted| *used > predicted))
+    {
+        eprintln!("! measured space exceeds the predicted bound");
+    }
+    let measured: SpaceClass = measured_class(execution_memory_usages);
+    if measured != estimate.class()
+    {
+        eprintln!("! measured space grows as {} but the prediction is {}", measured, estimate.class());
+    }
+    eprintln!();
+}
+
 #[allow(dead_code)]
 fn memory_profile_add(pretty_print_values: bool)
 {
     let mut device: Device = Device::new();
 
     device.load_program("examples/add.asm");
+    let estimate: SpaceEstimate = estimate_space(&decode_program(&device.program_lines));
 
     let mut input_lengths: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
     let mut execution_memory_usages: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
@@ -25,11 +87,12 @@ fn memory_profile_add(pretty_print_values: bool)
         device.load_input_variable("i00", input-1);
         device.load_input_variable("i01", 1);
         device.load_input_variable("i02", input);
-        device.execute_program(None);
+        let outcome: Outcome = device.execute_program(None);
         let (input_used, touched_flags, touched_registers) = device.count_touched_memory();
         input_lengths[i] = Variable::u32_to_bits(input).len();
         execution_memory_usages[i] = touched_flags + touched_registers;
-        println!("{},{},{}", Variable::u32_to_bits(input).len(), input_used, touched_flags + touched_registers);
+        println!("{},{},{},{},{}", Variable::u32_to_bits(input).len(), input_used, touched_flags + touched_registers,
+            estimate.total.evaluate(input_lengths[i]).map_or(String::new(), |predicted| predicted.to_string()), outcome.name());
         if pretty_print_values
         {
             eprintln!("i={}", input);
@@ -38,15 +101,7 @@ fn memory_profile_add(pretty_print_values: bool)
             eprintln!();
         }
     }
-    eprintln!("\n COMPLEXITY ANALYSIS");
-    eprintln!("{:->36}","");
-    eprintln!("{: ^14}|{: ^21}", "INPUT LENGTH", "MEMORY USED");
-    for i in 0..NUM_VALUES
-    {
-        eprintln!("{:->15}{:->21}", "+", "");
-        eprintln!(" {: <13}| {: <20}",  input_lengths[i], execution_memory_usages[i]);
-    }
-    eprintln!();
+    print_complexity_analysis(&input_lengths, &execution_memory_usages, &estimate);
 }
 
 #[allow(dead_code)]
@@ -55,6 +110,7 @@ fn memory_profile_lin_add(pretty_print_values: bool)
     let mut device: Device = Device::new();
 
     device.load_program("examples/lin-add.asm");
+    let estimate: SpaceEstimate = estimate_space(&decode_program(&device.program_lines));
 
     let mut input_lengths: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
     let mut execution_memory_usages: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
@@ -66,11 +122,12 @@ fn memory_profile_lin_add(pretty_print_values: bool)
         device.load_input_variable("i00", input | (1u32));
         device.load_input_variable("i01", 0);
         device.load_input_variable("i02", input | (1u32));
-        device.execute_program(None);
+        let outcome: Outcome = device.execute_program(None);
         let (input_used, touched_flags, touched_registers) = device.count_touched_memory();
         input_lengths[i] = Variable::u32_to_bits(input).len();
         execution_memory_usages[i] = touched_flags + touched_registers;
-        println!("{},{},{}", Variable::u32_to_bits(input).len(), input_used, touched_flags + touched_registers);
+        println!("{},{},{},{},{}", Variable::u32_to_bits(input).len(), input_used, touched_flags + touched_registers,
+            estimate.total.evaluate(input_lengths[i]).map_or(String::new(), |predicted| predicted.to_string()), outcome.name());
         if pretty_print_values
         {
             eprintln!("i={}", input);
@@ -79,15 +136,7 @@ fn memory_profile_lin_add(pretty_print_values: bool)
             eprintln!();
         }
     }
-    eprintln!("\n COMPLEXITY ANALYSIS");
-    eprintln!("{:->36}","");
-    eprintln!("{: ^14}|{: ^21}", "INPUT LENGTH", "MEMORY USED");
-    for i in 0..NUM_VALUES
-    {
-        eprintln!("{:->15}{:->21}", "+", "");
-        eprintln!(" {: <13}| {: <20}",  input_lengths[i], execution_memory_usages[i]);
-    }
-    eprintln!();
+    print_complexity_analysis(&input_lengths, &execution_memory_usages, &estimate);
 }
 
 #[allow(dead_code)]
@@ -96,6 +145,7 @@ fn memory_profile_pal_add(pretty_print_values: bool)
     let mut device: Device = Device::new();
 
     device.load_program("examples/pal-add.asm");
+    let estimate: SpaceEstimate = estimate_space(&decode_program(&device.program_lines));
 
     let mut input_lengths: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
     let mut execution_memory_usages: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
@@ -107,11 +157,12 @@ fn memory_profile_pal_add(pretty_print_values: bool)
         device.load_input_variable("i00", input | (1u32));
         device.load_input_variable("i01", 0);
         device.load_input_variable("i02", input | (1u32));
-        device.execute_program(None);
+        let outcome: Outcome = device.execute_program(None);
         let (input_used, touched_flags, touched_registers) = device.count_touched_memory();
         input_lengths[i] = Variable::u32_to_bits(input).len();
         execution_memory_usages[i] = touched_flags + touched_registers;
-        println!("{},{},{}", Variable::u32_to_bits(input).len(), input_used, touched_flags + touched_registers);
+        println!("{},{},{},{},{}", Variable::u32_to_bits(input).len(), input_used, touched_flags + touched_registers,
+            estimate.total.evaluate(input_lengths[i]).map_or(String::new(), |predicted| predicted.to_string()), outcome.name());
         if pretty_print_values
         {
             eprintln!("i={}", input);
@@ -120,15 +171,7 @@ fn memory_profile_pal_add(pretty_print_values: bool)
             eprintln!();
         }
     }
-    eprintln!("\n COMPLEXITY ANALYSIS");
-    eprintln!("{:->36}","");
-    eprintln!("{: ^14}|{: ^21}", "INPUT LENGTH", "MEMORY USED");
-    for i in 0..NUM_VALUES
-    {
-        eprintln!("{:->15}{:->21}", "+", "");
-        eprintln!(" {: <13}| {: <20}",  input_lengths[i], execution_memory_usages[i]);
-    }
-    eprintln!();
+    print_complexity_analysis(&input_lengths, &execution_memory_usages, &estimate);
 }
 
 #[allow(dead_code)]
@@ -137,6 +180,7 @@ fn memory_profile_pal(pretty_print_values: bool)
     let mut device: Device = Device::new();
 
     device.load_program("examples/pal.asm");
+    let estimate: SpaceEstimate = estimate_space(&decode_program(&device.program_lines));
 
     let mut input_lengths: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
     let mut execution_memory_usages: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
@@ -146,11 +190,12 @@ fn memory_profile_pal(pretty_print_values: bool)
         let input: u32 = VALUES_FOR_PROFILING[i];
         device.clear_device_execution_memory();
         device.load_input_variable("i00", input | (1u32));
-        device.execute_program(None);
+        let outcome: Outcome = device.execute_program(None);
         let (input_used, touched_flags, touched_registers) = device.count_touched_memory();
         input_lengths[i] = Variable::u32_to_bits(input).len();
         execution_memory_usages[i] = touched_flags + touched_registers;
-        println!("{},{},{}", Variable::u32_to_bits(input).len(), input_used, touched_flags + touched_registers);
+        println!("{},{},{},{},{}", Variable::u32_to_bits(input).len(), input_used, touched_flags + touched_registers,
+            estimate.total.evaluate(input_lengths[i]).map_or(String::new(), |predicted| predicted.to_string()), outcome.name());
         if pretty_print_values
         {
             eprintln!("i={}", input);
@@ -159,13 +204,54 @@ fn memory_profile_pal(pretty_print_values: bool)
             eprintln!();
         }
     }
+    print_complexity_analysis(&input_lengths, &execution_memory_usages, &estimate);
+}
+
+// the two-tape palindrome machine next to pal.asm on the same inputs
+#[allow(dead_code)]
+fn memory_profile_pal_tm(pretty_print_values: bool)
+{
+    let mut machine: TuringMachine = TuringMachine::new();
+    let mut device: Device = Device::new();
+
+    machine.load_program("examples/pal.tm");
+    device.load_program("examples/pal.asm");
+
+    let mut input_lengths: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
+    let mut machine_memory_usages: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
+    let mut device_memory_usages: [usize; NUM_VALUES] = [0usize; NUM_VALUES];
+
+    for i in 0..NUM_VALUES
+    {
+        let input: u32 = VALUES_FOR_PROFILING[i] | 1u32;
+        machine.load_input(&InputValue::Number(input));
+        let outcome: Outcome = machine.execute_program(None);
+        let (input_used, state_bits, work_bits) = machine.count_touched_memory();
+
+        device.clear_device_execution_memory();
+        device.load_input_variable("i00", input);
+        device.execute_program(None);
+        let (_, touched_flags, touched_registers) = device.count_touched_memory();
+
+        input_lengths[i] = Variable::u32_to_bits(input).len();
+        machine_memory_usages[i] = state_bits + work_bits;
+        device_memory_usages[i] = touched_flags + touched_registers;
+        println!("{},{},{},{},{}", input_lengths[i], input_used, machine_memory_usages[i], machine.steps_executed, outcome.name());
+        if pretty_print_values
+        {
+            eprintln!("i={}", input);
+            eprintln!("{:->36}","");
+            machine.pretty_print_memory();
+            eprintln!();
+        }
+    }
     eprintln!("\n COMPLEXITY ANALYSIS");
-    eprintln!("{:->36}","");
-    eprintln!("{: ^14}|{: ^21}", "INPUT LENGTH", "MEMORY USED");
+    eprintln!("{:->50}","");
+    eprintln!("{: ^14}|{: ^17}|{: ^17}", "INPUT LENGTH", "TM MEMORY", "MUNIN MEMORY");
     for i in 0..NUM_VALUES
     {
-        eprintln!("{:->15}{:->21}", "+", "");
-        eprintln!(" {: <13}| {: <20}",  input_lengths[i], execution_memory_usages[i]);
+        eprintln!("{:->15}{:->18}{:->17}", "+", "+", "");
+        eprintln!(" {: <13}| {: <16}| {: <16}",  input_lengths[i], machine_memory_usages[i], device_memory_usages[i]);
     }
     eprintln!();
 }
@@ -218,6 +304,13 @@ fn main() {
             eprintln!("{:->36}","");
             memory_profile_pal(pretty_print_values);
         }
+        4 =>
+        {
+            eprintln!("\n{:->36}","");
+            eprintln!(" COMPLEXITY ANALYSIS OF PAL (TURING MACHINE)");
+            eprintln!("{:->36}","");
+            memory_profile_pal_tm(pretty_print_values);
+        }
         _other=>
         {
             println!();
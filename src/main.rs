@@ -1,8 +1,37 @@
-use clap::Parser;
+// functions end in an explicit `return`, as in the library crates
+#![allow(clippy::needless_return)]
 
+use std::io::Write;
+
+use clap::{
+    Args as ClapArgs,
+    Parser,
+    Subcommand,
+};
+
+use munin_assembler::optimize_program;
 use munin_device::{
+    compose,
+    decode_program,
+    load_input_file,
+    ArithmeticMode,
+    BitOrder,
+    ControlFlowGraph,
+    estimate_space,
+    lint_program,
+    monte_carlo,
+    search,
     Device,
-    Variable
+    Diagnostic,
+    InputAccess,
+    InputValue,
+    Operand,
+    SearchOrder,
+    Severity,
+    SpaceEstimate,
+    TuringMachine,
+    Variable,
+    NUM_FLAGS,
 };
 
 const VALUES_FOR_PROFILING: [u32; 5] = [0x1, 0x2, 0x8, 0x80, 0x8000];
@@ -171,35 +200,564 @@ fn memory_profile_pal(pretty_print_values: bool)
 }
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// File to run
-    #[arg(short, long)]
-    file: String,
+    #[arg(short, long, required = true)]
+    file: Option<String>,
 
-    // Inputs
+    /// Inputs: numbers, or bitstrings written 0b0010 or bits:0010
     #[arg(short, long, num_args(0..))]
-    input: Vec<u32>
+    input: Vec<String>,
+
+    /// Files of inputs, written FORMAT:PATH with FORMAT raw, bits, hex or lines (the default); loaded after -i
+    #[arg(short = 'F', long, num_args(0..))]
+    input_file: Vec<String>,
+
+    /// Which end of a bitstring input is bit 0: msb (the last character) or lsb (the first)
+    #[arg(long, default_value_t = BitOrder::MsbFirst)]
+    bit_order: BitOrder,
+
+    /// Run the program through the peephole optimizer first
+    #[arg(short = 'O', long)]
+    optimize: bool,
+
+    #[command(flatten)]
+    setup: DeviceSetup,
+
+    /// Stop the run after this many instructions, so that it can be saved and resumed
+    #[arg(long)]
+    pause_at: Option<u32>,
+
+    /// File to save the device to after the run
+    #[arg(long)]
+    save_snapshot: Option<String>,
 }
 
-fn main() {
-    let args: Args = Args::parse();
-    let inputs = args.input;
-    let file_path = args.file;
+// the flags that set up a device before a run, shared by every command that
+// runs a program
+#[derive(ClapArgs, Debug)]
+struct DeviceSetup {
+    /// Integer semantics: unsigned, wrapping:WIDTH, saturating or signed
+    #[arg(short, long, default_value_t = ArithmeticMode::Unsigned)]
+    arithmetic: ArithmeticMode,
+
+    /// How execution code reads inputs: random, two-way or streaming
+    #[arg(long, default_value_t = InputAccess::RandomAccess)]
+    input_access: InputAccess,
+
+    /// Bits for `guess` to write, first guess first, such as a certificate from `search`; later guesses write 0
+    #[arg(short, long, default_value = "")]
+    guesses: String,
+
+    /// Seed of the random bits `rand` writes
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check a program for errors without running it
+    Lint {
+        /// File to check
+        #[arg(short, long)]
+        file: String,
+    },
+    /// Predict the space a program uses from its text
+    Bounds {
+        /// File to analyse
+        #[arg(short, long)]
+        file: String,
+    },
+    /// Write the control-flow graph of a program as Graphviz DOT
+    Cfg {
+        /// File to draw
+        #[arg(short, long)]
+        file: String,
+
+        /// Inputs to run the program on to colour the graph by execution counts
+        #[arg(short, long, num_args(0..))]
+        input: Option<Vec<String>>,
+
+        /// Files of inputs to run the program on, loaded after -i
+        #[arg(short = 'F', long, num_args(0..))]
+        input_file: Vec<String>,
+
+        /// Which end of a bitstring input is bit 0
+        #[arg(long, default_value_t = BitOrder::MsbFirst)]
+        bit_order: BitOrder,
+
+        /// File to write the graph to instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Run a program on the output of another without storing that output
+    Compose {
+        /// Program whose output is the input of the second
+        #[arg(short, long)]
+        first: String,
+
+        /// Program that reads the first program's output as i0
+        #[arg(short, long)]
+        second: String,
+
+        /// Inputs of the first program
+        #[arg(short, long, num_args(0..))]
+        input: Vec<String>,
+
+        /// Files of inputs of the first program, loaded after -i
+        #[arg(short = 'F', long, num_args(0..))]
+        input_file: Vec<String>,
+
+        /// Which end of a bitstring input is bit 0
+        #[arg(long, default_value_t = BitOrder::MsbFirst)]
+        bit_order: BitOrder,
+
+        /// How the second program reads its input: two-way or streaming
+        #[arg(long, default_value_t = InputAccess::TwoWay)]
+        input_access: InputAccess,
+    },
+    /// Search every run of a program that guesses for one that accepts
+    Search {
+        /// File to run
+        #[arg(short, long)]
+        file: String,
+
+        /// Inputs: numbers, or bitstrings written 0b0010 or bits:0010
+        #[arg(short, long, num_args(0..))]
+        input: Vec<String>,
+
+        /// Files of inputs, loaded after -i
+        #[arg(short = 'F', long, num_args(0..))]
+        input_file: Vec<String>,
+
+        /// Which end of a bitstring input is bit 0
+        #[arg(long, default_value_t = BitOrder::MsbFirst)]
+        bit_order: BitOrder,
+
+        /// Order to explore runs in: bfs or dfs
+        #[arg(long, default_value_t = SearchOrder::BreadthFirst)]
+        order: SearchOrder,
+
+        #[command(flatten)]
+        setup: DeviceSetup,
+    },
+    /// Run a randomised program many times and estimate how often it accepts
+    MonteCarlo {
+        /// File to run
+        #[arg(short, long)]
+        file: String,
+
+        /// Inputs: numbers, or bitstrings written 0b0010 or bits:0010
+        #[arg(short, long, num_args(0..))]
+        input: Vec<String>,
+
+        /// Files of inputs, loaded after -i
+        #[arg(short = 'F', long, num_args(0..))]
+        input_file: Vec<String>,
+
+        /// Which end of a bitstring input is bit 0
+        #[arg(long, default_value_t = BitOrder::MsbFirst)]
+        bit_order: BitOrder,
+
+        /// Number of runs
+        #[arg(short, long, default_value_t = 100)]
+        runs: u32,
+
+        #[command(flatten)]
+        setup: DeviceSetup,
+    },
+    /// Load a saved device and carry on with its run
+    Resume {
+        /// Snapshot to load
+        #[arg(short, long)]
+        snapshot: String,
+
+        /// Stop again once this many instructions have run in total
+        #[arg(long)]
+        pause_at: Option<u32>,
+
+        /// File to save the device to after the run
+        #[arg(long)]
+        save_snapshot: Option<String>,
+    },
+    /// Step through a program forwards and backwards
+    Debug {
+        /// File to run
+        #[arg(short, long)]
+        file: String,
+
+        /// Inputs: numbers, or bitstrings written 0b0010 or bits:0010
+        #[arg(short, long, num_args(0..))]
+        input: Vec<String>,
+
+        /// Files of inputs, loaded after -i
+        #[arg(short = 'F', long, num_args(0..))]
+        input_file: Vec<String>,
+
+        /// Which end of a bitstring input is bit 0
+        #[arg(long, default_value_t = BitOrder::MsbFirst)]
+        bit_order: BitOrder,
+
+        /// Most instructions that can be stepped back over
+        #[arg(long, default_value_t = 1000000)]
+        history: usize,
+
+        #[command(flatten)]
+        setup: DeviceSetup,
+    },
+    /// Run a multi-tape Turing machine description
+    Tm {
+        /// Machine to run
+        #[arg(short, long)]
+        file: String,
+
+        /// Input written on the input tape, bit 0 first
+        #[arg(short, long, default_value = "0")]
+        input: String,
+
+        /// Which end of a bitstring input is bit 0
+        #[arg(long, default_value_t = BitOrder::MsbFirst)]
+        bit_order: BitOrder,
+
+        /// Stop after this many steps
+        #[arg(short, long)]
+        max_steps: Option<u32>,
+    },
+}
+
+fn lint(file_path: String)
+{
+    let mut device = Device::new();
+
+    device.load_program(file_path);
+
+    let diagnostics: Vec<Diagnostic> = lint_program(&decode_program(&device.program_lines));
+
+    for diagnostic in diagnostics.iter()
+    {
+        println!("{}", diagnostic);
+    }
+
+    if diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    {
+        std::process::exit(1);
+    }
+}
+
+fn bounds(file_path: String)
+{
+    let mut device = Device::new();
+
+    device.load_program(file_path);
+
+    let estimate: SpaceEstimate = estimate_space(&decode_program(&device.program_lines));
+
+    eprintln!(" PREDICTED SPACE");
+    eprintln!("{:->36}","");
+    eprintln!("{: ^10}|{: ^25}", "MEMORY", "BITS USED");
+    eprintln!("{:->11}{:->25}", "+", "");
+    for (index, width) in estimate.variables.iter()
+    {
+        eprintln!("v{:02}{: <7}| {}", index, "", width);
+    }
+    eprintln!("{: <10}| {}", "BITS", estimate.num_bits);
+    eprintln!("{: <10}| {}", "FLAGS", NUM_FLAGS);
+    eprintln!("{:->11}{:->25}", "+", "");
+    eprintln!("{: <10}| {}", "TOTAL", estimate.total);
+    eprintln!("{: <10}| {}", "CLASS", estimate.class());
+}
+
+fn parse_inputs(inputs: &[String], input_files: &[String], order: BitOrder) -> Vec<InputValue>
+{
+    let mut values: Vec<InputValue> = inputs.iter()
+        .map(|input| InputValue::parse(input, order).unwrap_or_else(|error| panic!("{}", error)))
+        .collect::<Vec<InputValue>>();
+
+    for input_file in input_files
+    {
+        values.extend(load_input_file(input_file, order).unwrap_or_else(|error| panic!("{}", error)));
+    }
+
+    return values;
+}
+
+fn cfg(file_path: String, inputs: Option<Vec<InputValue>>, output_path: Option<String>)
+{
+    let mut device = Device::new();
+
+    device.load_program(file_path);
+
+    let graph: ControlFlowGraph = ControlFlowGraph::build(&decode_program(&device.program_lines));
+
+    let dot: String = match inputs
+    {
+        Some(inputs) =>
+        {
+            device.load_inputs(&inputs);
+
+            device.record_profile = true;
+            device.execute_program(None);
+
+            graph.to_dot(Some(&device.profile))
+        }
+        None => graph.to_dot(None),
+    };
+
+    match output_path
+    {
+        Some(output_path) => std::fs::write(output_path, dot).expect("unable to write"),
+        None => print!("{}", dot),
+    }
+}
 
+fn compose_programs(first_path: String, second_path: String, inputs: Vec<InputValue>, input_access: InputAccess)
+{
+    let mut first = Device::new();
+    let mut second = Device::new();
+
+    first.load_program(first_path);
+    first.load_inputs(&inputs);
+
+    second.load_program(second_path);
+    second.input_access = input_access;
+
+    eprintln!("{}", compose(first, second));
+}
+
+fn search_runs(file_path: String, inputs: Vec<InputValue>, order: SearchOrder, setup: DeviceSetup)
+{
+    let mut device = Device::new();
+
+    setup_device(&mut device, &setup);
+    device.load_program(file_path);
+    device.load_inputs(&inputs);
+
+    eprintln!("{}", search(&mut device, order));
+}
+
+// the seed of every run is drawn from `--seed`
+fn monte_carlo_runs(file_path: String, inputs: Vec<InputValue>, runs: u32, setup: DeviceSetup)
+{
     let mut device = Device::new();
 
-    let mut index: u32 = 0;
-    for input in inputs
+    setup_device(&mut device, &setup);
+    device.load_program(file_path);
+    device.load_inputs(&inputs);
+
+    eprintln!("{}", monte_carlo(&mut device, runs, setup.seed));
+}
+
+fn resume(snapshot_path: String, pause_at: Option<u32>, save_path: Option<String>)
+{
+    let mut device = Device::load_snapshot(snapshot_path).unwrap_or_else(|error| panic!("{}", error));
+
+    device.pause_at = pause_at;
+    device.resume();
+
+    device.pretty_print_memory();
+
+    if let Some(save_path) = save_path
+    {
+        device.save_snapshot(save_path);
+    }
+}
+
+fn print_position(device: &Device)
+{
+    match device.program_lines.get(device.instruction_pointer)
+    {
+        Some(line) if device.program_running => eprintln!("{} [{}]: {}", device.instruction_pointer, device.instructions_executed, line),
+        _other => eprintln!("finished: {} [{}]", device.outcome(), device.instructions_executed),
+    }
+}
+
+fn debug(file_path: String, inputs: Vec<InputValue>, history: usize, setup: DeviceSetup)
+{
+    let mut device = Device::new();
+
+    setup_device(&mut device, &setup);
+    device.load_program(file_path);
+    device.load_inputs(&inputs);
+
+    device.history_limit = history;
+    device.pause_at = Some(0);
+    device.execute_program(None);
+    device.pause_at = None;
+
+    print_position(&device);
+
+    let mut line: String = String::new();
+    loop
+    {
+        print!("(munin) ");
+        std::io::stdout().flush().expect("unable to write");
+
+        line.clear();
+        if std::io::stdin().read_line(&mut line).expect("unable to read") == 0
+        {
+            break;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect::<Vec<&str>>();
+        let count: Option<u32> = words.get(1).and_then(|count| count.parse::<u32>().ok());
+
+        match words.first().copied()
+        {
+            Some("step" | "s") => device.step(count.unwrap_or(1)),
+            Some("continue" | "c") => { device.resume(); }
+            Some("back" | "b") =>
+            {
+                let steps: usize = count.unwrap_or(1) as usize;
+                if device.back(steps) < steps
+                {
+                    eprintln!("history only went back that far");
+                }
+            }
+            Some("reverse-continue" | "rc") =>
+            {
+                match words.get(1).and_then(|register| Operand::parse(register))
+                {
+                    Some(register @ (Operand::Variable(_) | Operand::Bit(_))) =>
+                    {
+                        if !device.reverse_to_write(&register)
+                        {
+                            eprintln!("no write of {} in the history", register);
+                        }
+                    }
+                    _other => eprintln!("usage: reverse-continue vX"),
+                }
+            }
+            Some("print" | "p") => device.pretty_print_memory(),
+            Some("where" | "w") => {}
+            Some("quit" | "q") => break,
+            None => continue,
+            Some(_) =>
+            {
+                eprintln!("commands: step [N], back [N], reverse-continue vX, continue, where, print, quit");
+                continue;
+            }
+        }
+
+        print_position(&device);
+    }
+}
+
+fn parse_guesses(guesses: &str) -> Vec<bool>
+{
+    return guesses.chars()
+        .map(|guess| match guess
+        {
+            '0' => false,
+            '1' => true,
+            _other => panic!("Invalid guess `{}`; guesses are written 0 and 1", guess),
+        })
+        .collect::<Vec<bool>>();
+}
+
+fn setup_device(device: &mut Device, setup: &DeviceSetup)
+{
+    device.arithmetic = setup.arithmetic;
+    device.input_access = setup.input_access;
+    device.guesses = parse_guesses(&setup.guesses);
+    device.seed = setup.seed;
+}
+
+fn tm(file_path: String, input: InputValue, max_steps: Option<u32>)
+{
+    let mut machine = TuringMachine::new();
+
+    machine.load_program(file_path);
+    machine.load_input(&input);
+    machine.execute_program(max_steps);
+
+    machine.pretty_print_memory();
+}
+
+fn main() {
+    let args: Args = Args::parse();
+
+    match args.command
     {
-        device.load_input_variable(&format!("i{index}"), input);
-        index += 1;
+        Some(Command::Lint { file }) =>
+        {
+            lint(file);
+            return;
+        }
+        Some(Command::Bounds { file }) =>
+        {
+            bounds(file);
+            return;
+        }
+        Some(Command::Cfg { file, input, input_file, output, bit_order }) =>
+        {
+            let inputs: Option<Vec<InputValue>> = match (input, input_file.is_empty())
+            {
+                (None, true) => None,
+                (input, _) => Some(parse_inputs(&input.unwrap_or_default(), &input_file, bit_order)),
+            };
+            cfg(file, inputs, output);
+            return;
+        }
+        Some(Command::Compose { first, second, input, input_file, bit_order, input_access }) =>
+        {
+            compose_programs(first, second, parse_inputs(&input, &input_file, bit_order), input_access);
+            return;
+        }
+        Some(Command::Search { file, input, input_file, bit_order, order, setup }) =>
+        {
+            search_runs(file, parse_inputs(&input, &input_file, bit_order), order, setup);
+            return;
+        }
+        Some(Command::MonteCarlo { file, input, input_file, bit_order, runs, setup }) =>
+        {
+            monte_carlo_runs(file, parse_inputs(&input, &input_file, bit_order), runs, setup);
+            return;
+        }
+        Some(Command::Resume { snapshot, pause_at, save_snapshot }) =>
+        {
+            resume(snapshot, pause_at, save_snapshot);
+            return;
+        }
+        Some(Command::Debug { file, input, input_file, bit_order, history, setup }) =>
+        {
+            debug(file, parse_inputs(&input, &input_file, bit_order), history, setup);
+            return;
+        }
+        Some(Command::Tm { file, input, bit_order, max_steps }) =>
+        {
+            let input: InputValue = InputValue::parse(&input, bit_order).unwrap_or_else(|error| panic!("{}", error));
+            tm(file, input, max_steps);
+            return;
+        }
+        None => {}
     }
 
+    let inputs: Vec<InputValue> = parse_inputs(&args.input, &args.input_file, args.bit_order);
+    let file_path = args.file.expect("no file to run");
+
+    let mut device = Device::new();
+    setup_device(&mut device, &args.setup);
+    device.pause_at = args.pause_at;
+
+    device.load_inputs(&inputs);
+
     device.load_program(file_path);
 
+    if args.optimize
+    {
+        device.program_lines = optimize_program(&device.program_lines);
+    }
+
     device.execute_program(None);
 
     device.pretty_print_memory();
 
+    if let Some(save_path) = args.save_snapshot
+    {
+        device.save_snapshot(save_path);
+    }
 }